@@ -0,0 +1,336 @@
+//! Helpers to interact with arbitrary contracts using their ABI.
+
+use std::path::Path;
+
+use color_eyre::eyre::{self, Context};
+use color_eyre::Result;
+use ethers::abi::token::{LenientTokenizer, Tokenizer};
+use ethers::abi::{Abi, Function, Param, ParamType, StateMutability, Token};
+use ethers::types::Bytes;
+use inquire::validator;
+
+use crate::config::Contact;
+
+/// Load an ABI from a JSON file.
+///
+/// The file could be either a plain ABI array, or a compiled contract
+/// artifact (Foundry, Hardhat, ..etc) that has an `abi` field.
+pub fn load_from_file(path: impl AsRef<Path>) -> Result<Abi> {
+    let abi = match read_json(path.as_ref())? {
+        serde_json::Value::Object(mut artifact) => artifact
+            .remove("abi")
+            .ok_or_else(|| eyre::eyre!("No ABI found in the artifact"))?,
+        abi => abi,
+    };
+    let abi = serde_json::from_value(abi).context("Invalid ABI")?;
+    Ok(abi)
+}
+
+/// A compiled contract, ready to be deployed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Artifact {
+    pub abi: Abi,
+    pub bytecode: Bytes,
+    /// Whether the constructor accepts a value, which `Abi` does not keep.
+    pub payable_constructor: bool,
+}
+
+/// Load the ABI and the creation bytecode from a compiled contract
+/// artifact.
+///
+/// Both Foundry's `out/*.json` (`bytecode.object`) and Hardhat's
+/// (`bytecode`) artifacts are supported.
+pub fn load_artifact(path: impl AsRef<Path>) -> Result<Artifact> {
+    let mut artifact = match read_json(path.as_ref())? {
+        serde_json::Value::Object(artifact) => artifact,
+        _ => eyre::bail!("Expected a compiled contract artifact"),
+    };
+    let abi = artifact
+        .remove("abi")
+        .ok_or_else(|| eyre::eyre!("No ABI found in the artifact"))?;
+    // `payable` is the pre 0.5 Solidity field.
+    let payable_constructor =
+        abi.as_array().into_iter().flatten().any(|item| {
+            item["type"] == "constructor"
+                && (item["stateMutability"] == "payable"
+                    || item["payable"] == true)
+        });
+    let abi = serde_json::from_value(abi).context("Invalid ABI")?;
+    let bytecode = match artifact.remove("bytecode") {
+        Some(serde_json::Value::Object(mut bytecode)) => {
+            bytecode.remove("object")
+        }
+        bytecode => bytecode,
+    }
+    .and_then(|b| b.as_str().map(ToOwned::to_owned))
+    .ok_or_else(|| eyre::eyre!("No bytecode found in the artifact"))?;
+    let bytecode = bytecode
+        .parse::<Bytes>()
+        .map_err(|e| eyre::eyre!("Invalid bytecode: {}", e))?;
+    if bytecode.is_empty() {
+        eyre::bail!("The artifact bytecode is empty, is it an interface?");
+    }
+    Ok(Artifact {
+        abi,
+        bytecode,
+        payable_constructor,
+    })
+}
+
+fn read_json(path: &Path) -> Result<serde_json::Value> {
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let json = serde_json::from_reader(file)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(json)
+}
+
+/// Returns `true` if calling the function could change the chain state.
+pub fn is_mutable(function: &Function) -> bool {
+    matches!(
+        function.state_mutability,
+        StateMutability::NonPayable | StateMutability::Payable
+    )
+}
+
+/// Human readable representation of the function, for example
+/// `transfer(address recipient, uint256 amount)`.
+pub fn display_function(function: &Function) -> String {
+    format!("{}({})", function.name, display_params(&function.inputs))
+}
+
+fn display_params(params: &[Param]) -> String {
+    params
+        .iter()
+        .map(|p| {
+            if p.name.is_empty() {
+                p.kind.to_string()
+            } else {
+                format!("{} {}", p.kind, p.name)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Asks the user to select one of the ABI functions that matches the
+/// `filter`.
+pub fn select_function(
+    message: &str,
+    abi: &Abi,
+    filter: impl Fn(&Function) -> bool,
+) -> Result<Function> {
+    let functions = abi.functions().filter(|f| filter(f)).collect::<Vec<_>>();
+    if functions.is_empty() {
+        eyre::bail!("No functions found in the ABI");
+    }
+    let functions_display = functions
+        .iter()
+        .map(|f| display_function(f))
+        .collect::<Vec<_>>();
+    let selected_function =
+        inquire::Select::new(message, functions_display.clone()).prompt()?;
+    let i = functions_display
+        .iter()
+        .position(|f| f == &selected_function)
+        .unwrap_or_default();
+    Ok(functions[i].clone())
+}
+
+/// Prompts the user for the value of each of the given parameters.
+pub fn prompt_arguments(
+    params: &[Param],
+    contacts: &[Contact],
+    require_checksum: bool,
+) -> Result<Vec<Token>> {
+    let mut tokens = Vec::with_capacity(params.len());
+    for (i, param) in params.iter().enumerate() {
+        let name = if param.name.is_empty() {
+            format!("arg{i}")
+        } else {
+            param.name.clone()
+        };
+        let message = format!("{name} ({})", param.kind);
+        let prompt = inquire::Text::new(&message)
+            .with_validator(TokenValidator::new(
+                param.kind.clone(),
+                require_checksum,
+            ))
+            .with_help_message(help_message(&param.kind));
+        let value = if param.kind == ParamType::Address {
+            prompt
+                .with_autocomplete(crate::state::AddressBookAutoComplete::new(
+                    contacts.to_vec(),
+                ))
+                .prompt()?
+        } else {
+            prompt.prompt()?
+        };
+        tokens.push(tokenize(&param.kind, &value)?);
+    }
+    Ok(tokens)
+}
+
+fn help_message(kind: &ParamType) -> &'static str {
+    match kind {
+        ParamType::Address => "An address or a contact",
+        ParamType::Uint(_) => "A number, units are supported, e.g. 1.5 ether",
+        ParamType::Int(_) => "A signed number",
+        ParamType::Bool => "true or false",
+        ParamType::Bytes | ParamType::FixedBytes(_) => "Hex encoded bytes",
+        ParamType::String => "Any text",
+        ParamType::Array(_) | ParamType::FixedArray(..) => {
+            "Comma separated values, e.g. [1,2,3]"
+        }
+        ParamType::Tuple(_) => "Comma separated values, e.g. (1,0x..,true)",
+    }
+}
+
+/// Human readable representation of the ABI token.
+pub fn display_token(token: &Token) -> String {
+    match token {
+        Token::Address(address) => ethers::utils::to_checksum(address, None),
+        Token::FixedBytes(bytes) | Token::Bytes(bytes) => {
+            format!("0x{}", hex::encode(bytes))
+        }
+        Token::Int(n) => ethers::types::I256::from_raw(*n).to_string(),
+        Token::Uint(n) => n.to_string(),
+        Token::Bool(b) => b.to_string(),
+        Token::String(s) => format!("{s:?}"),
+        Token::FixedArray(tokens) | Token::Array(tokens) => {
+            let tokens = tokens.iter().map(display_token).collect::<Vec<_>>();
+            format!("[{}]", tokens.join(", "))
+        }
+        Token::Tuple(tokens) => {
+            let tokens = tokens.iter().map(display_token).collect::<Vec<_>>();
+            format!("({})", tokens.join(", "))
+        }
+    }
+}
+
+/// Parse the user input into an ABI token of the given kind.
+pub fn tokenize(kind: &ParamType, value: &str) -> Result<Token> {
+    let value = value.trim();
+    let value = match kind {
+        // Allow using the contacts' "name | address" format.
+        ParamType::Address => value.rsplit(" | ").next().unwrap_or(value),
+        _ => value,
+    };
+    LenientTokenizer::tokenize(kind, value)
+        .map_err(|e| eyre::eyre!("Invalid {}: {}", kind, e))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct TokenValidator {
+    kind: ParamType,
+    require_checksum: bool,
+}
+
+impl TokenValidator {
+    fn new(kind: ParamType, require_checksum: bool) -> Self {
+        Self {
+            kind,
+            require_checksum,
+        }
+    }
+}
+
+impl validator::StringValidator for TokenValidator {
+    fn validate(
+        &self,
+        s: &str,
+    ) -> Result<validator::Validation, inquire::CustomUserError> {
+        let checked = match tokenize(&self.kind, s) {
+            Ok(Token::Address(address)) => {
+                let s = s.trim();
+                let s = s.rsplit(" | ").next().unwrap_or(s);
+                crate::state::check_address_checksum(
+                    s,
+                    address,
+                    self.require_checksum,
+                )
+            }
+            Ok(_) => Ok(()),
+            Err(e) => Err(e.to_string()),
+        };
+        match checked {
+            Ok(()) => Ok(validator::Validation::Valid),
+            Err(e) => Ok(validator::Validation::Invalid(
+                validator::ErrorMessage::Custom(e),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::{Address, U256};
+
+    use super::*;
+
+    #[test]
+    fn tokenize_contact_address() {
+        let token = tokenize(
+            &ParamType::Address,
+            "Burn | 0x0000000000000000000000000000000000000000",
+        )
+        .unwrap();
+        assert_eq!(token, Token::Address(Address::zero()));
+    }
+
+    #[test]
+    fn address_argument_checksum() {
+        let validator = TokenValidator::new(ParamType::Address, false);
+        let validate = |s| {
+            matches!(
+                validator::StringValidator::validate(&validator, s),
+                Ok(validator::Validation::Valid)
+            )
+        };
+        assert!(validate("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"));
+        assert!(validate("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"));
+        assert!(!validate("0x5aaeb6053F3E94C9b9A09f33669435E7Ef1BeAed"));
+        assert!(!validate(
+            "Friend | 0x5aaeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+        ));
+    }
+
+    #[test]
+    fn load_foundry_artifact() {
+        let path = std::env::temp_dir().join("shekozwallet-artifact.json");
+        let artifact = serde_json::json!({
+            "abi": [{
+                "type": "constructor",
+                "inputs": [{ "name": "supply", "type": "uint256" }],
+                "stateMutability": "payable"
+            }],
+            "bytecode": { "object": "0x6080", "linkReferences": {} }
+        });
+        std::fs::write(&path, artifact.to_string()).unwrap();
+        let artifact = load_artifact(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(artifact.abi.constructor().unwrap().inputs.len(), 1);
+        assert_eq!(artifact.bytecode.to_vec(), vec![0x60, 0x80]);
+        assert!(artifact.payable_constructor);
+    }
+
+    #[test]
+    fn display_tokens() {
+        let token = Token::Tuple(vec![
+            Token::Uint(U256::from(1_000)),
+            Token::Int(ethers::types::I256::from(-1).into_raw()),
+            Token::Array(vec![Token::Bool(true), Token::Bool(false)]),
+            Token::Bytes(vec![0xde, 0xad]),
+        ]);
+        assert_eq!(display_token(&token), "(1000, -1, [true, false], 0xdead)");
+    }
+
+    #[test]
+    fn tokenize_uint_with_units() {
+        let token = tokenize(&ParamType::Uint(256), "1.5 ether").unwrap();
+        let expected = ethers::utils::parse_ether("1.5").unwrap();
+        assert_eq!(token, Token::Uint(expected));
+        let token = tokenize(&ParamType::Uint(256), "42").unwrap();
+        assert_eq!(token, Token::Uint(U256::from(42)));
+    }
+}
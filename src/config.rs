@@ -21,10 +21,24 @@ pub struct Config {
     pub contacts: Vec<Contact>,
     #[serde(default)]
     pub debug: bool,
+    /// Spending policies, checked before signing any transaction.
+    #[serde(default)]
+    pub policies: Policies,
+    /// Keep an encrypted journal of the sent transactions, off by default
+    /// so the wallet leaves no traces.
+    #[serde(default)]
+    pub journal: bool,
+    /// Reject typed addresses without an EIP-55 checksum.
+    #[serde(default)]
+    pub require_checksum: bool,
+    /// Avoid lookups that are not needed to send a transaction, like the
+    /// reverse ENS names of the shown addresses.
+    #[serde(default)]
+    pub privacy_strict: bool,
     pub proxy: Option<String>,
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Network {
     pub rpc_url: url::Url,
     pub explorer_url: Option<url::Url>,
@@ -32,6 +46,41 @@ pub struct Network {
     pub currency_symbol: String,
     #[serde(default)]
     pub erc20_tokens: Vec<Erc20TokenConfig>,
+    /// The wrapped native token contract (WETH, WMATIC, ..etc).
+    #[serde(default)]
+    pub wrapped_native_token: Option<types::Address>,
+    /// Known contracts, with their ABIs.
+    #[serde(default)]
+    pub contracts: Vec<ContractConfig>,
+    /// The ENS registry, names are not resolved if not set.
+    #[serde(default)]
+    pub ens_registry: Option<types::Address>,
+    /// Etherscan compatible API, used to fetch the transactions history.
+    #[serde(default)]
+    pub explorer_api_url: Option<url::Url>,
+    /// The key of the explorer API, if it requires one.
+    #[serde(default)]
+    pub explorer_api_key: Option<String>,
+    /// Confirmations to wait for after sending a transaction, 1 if not set.
+    #[serde(default)]
+    pub confirmations: Option<u64>,
+    /// Seconds to wait for the confirmations, 600 if not set.
+    #[serde(default)]
+    pub confirmation_timeout: Option<u64>,
+    /// Chainlink USD price feeds, used to show the USD values.
+    #[serde(default)]
+    pub price_feeds: PriceFeeds,
+}
+
+/// Chainlink aggregators of the `TOKEN / USD` pairs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PriceFeeds {
+    /// The feed of the network currency.
+    #[serde(default)]
+    pub native: Option<types::Address>,
+    /// The feeds by ERC20 token address.
+    #[serde(default)]
+    pub tokens: HashMap<types::Address, types::Address>,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
@@ -41,6 +90,13 @@ pub struct Erc20TokenConfig {
     pub symbol: String,
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContractConfig {
+    pub address: types::Address,
+    pub name: String,
+    pub abi: ethers::abi::Abi,
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Contact {
     pub name: String,
@@ -49,13 +105,35 @@ pub struct Contact {
 
 impl fmt::Display for Erc20TokenConfig {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{} [{}] ({})", self.symbol, self.name, self.address)
+        write!(
+            f,
+            "{} [{}] ({})",
+            self.symbol,
+            self.name,
+            ethers::utils::to_checksum(&self.address, None)
+        )
+    }
+}
+
+impl fmt::Display for ContractConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({})",
+            self.name,
+            ethers::utils::to_checksum(&self.address, None)
+        )
     }
 }
 
 impl fmt::Display for Contact {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{} ({})", self.name, self.address)
+        write!(
+            f,
+            "{} ({})",
+            self.name,
+            ethers::utils::to_checksum(&self.address, None)
+        )
     }
 }
 
@@ -71,6 +149,27 @@ impl Default for Config {
             chain_id: 1.into(),
             currency_symbol: "ETH".to_string(),
             erc20_tokens: vec![],
+            wrapped_native_token: Some(
+                "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"
+                    .parse()
+                    .unwrap(),
+            ),
+            contracts: vec![],
+            ens_registry: Some(ethers::providers::ens::ENS_ADDRESS),
+            explorer_api_url: Some(
+                "https://api.etherscan.io/v2/api".parse().unwrap(),
+            ),
+            explorer_api_key: None,
+            confirmations: None,
+            confirmation_timeout: None,
+            price_feeds: PriceFeeds {
+                native: Some(
+                    "0x5f4eC3Df9cbd43714FE2740F5E3616155c5b8419"
+                        .parse()
+                        .unwrap(),
+                ),
+                tokens: HashMap::new(),
+            },
         };
         networks.insert("mainnet".to_string(), eth_mainnet);
         let polygon_mainnet = Network {
@@ -94,6 +193,27 @@ impl Default for Config {
                         .unwrap(),
                 },
             ],
+            wrapped_native_token: Some(
+                "0x0d500B1d8E8eF31E21C99d1Db9A6444d3ADf1270"
+                    .parse()
+                    .unwrap(),
+            ),
+            contracts: vec![],
+            ens_registry: None,
+            explorer_api_url: Some(
+                "https://api.etherscan.io/v2/api".parse().unwrap(),
+            ),
+            explorer_api_key: None,
+            confirmations: None,
+            confirmation_timeout: None,
+            price_feeds: PriceFeeds {
+                native: Some(
+                    "0xAB594600376Ec9fD91F8e885dADF0CE036862dE0"
+                        .parse()
+                        .unwrap(),
+                ),
+                tokens: HashMap::new(),
+            },
         };
         networks.insert("polygon".to_string(), polygon_mainnet);
         let bsc_mainnet = Network {
@@ -102,6 +222,20 @@ impl Default for Config {
             chain_id: 56.into(),
             currency_symbol: "BNB".to_string(),
             erc20_tokens: vec![],
+            wrapped_native_token: Some(
+                "0xbb4CdB9CBd36B01bD1cBaEBF2De08d9173bc095c"
+                    .parse()
+                    .unwrap(),
+            ),
+            contracts: vec![],
+            ens_registry: None,
+            explorer_api_url: Some(
+                "https://api.etherscan.io/v2/api".parse().unwrap(),
+            ),
+            explorer_api_key: None,
+            confirmations: None,
+            confirmation_timeout: None,
+            price_feeds: PriceFeeds::default(),
         };
         networks.insert("bsc".to_string(), bsc_mainnet);
         let local_network = Network {
@@ -110,6 +244,14 @@ impl Default for Config {
             chain_id: 1337.into(),
             currency_symbol: "ETH".to_string(),
             erc20_tokens: vec![],
+            wrapped_native_token: None,
+            contracts: vec![],
+            ens_registry: None,
+            explorer_api_url: None,
+            explorer_api_key: None,
+            confirmations: None,
+            confirmation_timeout: None,
+            price_feeds: PriceFeeds::default(),
         };
         networks.insert("local".to_string(), local_network);
         let contacts = vec![
@@ -121,6 +263,10 @@ impl Default for Config {
         ];
         Self {
             debug: false,
+            privacy_strict: false,
+            require_checksum: false,
+            policies: Policies::default(),
+            journal: false,
             networks,
             contacts,
             proxy: Some(tor_proxy),
@@ -129,6 +275,41 @@ impl Default for Config {
     }
 }
 
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Policies {
+    /// Policies per network, keyed by the network name.
+    #[serde(default)]
+    pub networks: HashMap<String, NetworkPolicy>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkPolicy {
+    /// Limits of the native currency.
+    #[serde(default)]
+    pub native: Option<SpendingLimit>,
+    /// Limits per ERC20 token address.
+    #[serde(default)]
+    pub tokens: HashMap<types::Address, SpendingLimit>,
+    /// Names of the contacts allowed to receive funds, any recipient is
+    /// allowed if empty.
+    #[serde(default)]
+    pub allowed_recipients: Vec<String>,
+}
+
+/// Spending limits, the amounts are in token units, e.g. `"1.5"`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpendingLimit {
+    /// Maximum amount of a single transaction.
+    #[serde(default)]
+    pub max_per_transaction: Option<String>,
+    /// Maximum total amount over the last 24 hours.
+    #[serde(default)]
+    pub max_daily: Option<String>,
+    /// Amount above which the user has to type a confirmation phrase.
+    #[serde(default)]
+    pub confirmation_threshold: Option<String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct Bip32XPub(pub XPub);
 
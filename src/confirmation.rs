@@ -0,0 +1,252 @@
+//! Waiting for a sent transaction to be confirmed, with a timeout, and
+//! detecting when it was replaced or dropped.
+
+use std::time::{Duration, Instant};
+
+use color_eyre::eyre;
+use color_eyre::Result;
+use ethers::providers::Middleware;
+use ethers::types::{
+    Address, BlockNumber, TransactionReceipt, H256, U256, U64,
+};
+
+use crate::config::Network;
+use crate::state::EthersClient;
+
+/// Confirmations required when the network does not configure any.
+const DEFAULT_CONFIRMATIONS: u64 = 1;
+
+/// Seconds to wait for the confirmations when the network does not
+/// configure any timeout.
+const DEFAULT_TIMEOUT_SECS: u64 = 600;
+
+/// Interval between the node polls.
+const POLL_INTERVAL: Duration = Duration::from_secs(4);
+
+/// The confirmations required by the network, at least one.
+pub fn confirmations(network: &Network) -> u64 {
+    network
+        .confirmations
+        .unwrap_or(DEFAULT_CONFIRMATIONS)
+        .max(1)
+}
+
+/// The time to wait for the confirmations, at least one poll interval.
+pub fn timeout(network: &Network) -> Duration {
+    Duration::from_secs(
+        network.confirmation_timeout.unwrap_or(DEFAULT_TIMEOUT_SECS),
+    )
+    .max(POLL_INTERVAL)
+}
+
+/// The state of a sent transaction, at a poll.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Progress {
+    /// Not mined yet.
+    Pending,
+    /// Mined, with fewer confirmations than required.
+    Confirming(u64),
+    /// Mined, with the required confirmations.
+    Confirmed,
+    /// Not mined, but its nonce was used by another transaction.
+    Replaced,
+}
+
+impl Progress {
+    /// `mined_in` is the block of the receipt, if any, and `account_nonce`
+    /// the number of the sender transactions mined in the `latest` block.
+    pub fn new(
+        mined_in: Option<u64>,
+        latest: u64,
+        account_nonce: U256,
+        tx_nonce: U256,
+        required: u64,
+    ) -> Self {
+        match mined_in {
+            Some(block) => {
+                let confirmations = latest.saturating_sub(block) + 1;
+                if confirmations >= required {
+                    Self::Confirmed
+                } else {
+                    Self::Confirming(confirmations)
+                }
+            }
+            None if account_nonce > tx_nonce => Self::Replaced,
+            None => Self::Pending,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Outcome {
+    /// Mined with the required confirmations, the receipt status could
+    /// still be a failure.
+    Mined(Box<TransactionReceipt>),
+    /// The nonce was used by another transaction, this one will never be
+    /// mined.
+    Replaced,
+    /// Still not confirmed after the timeout.
+    TimedOut,
+}
+
+/// Polls the node until the transaction is confirmed, replaced or the
+/// network timeout is reached, `on_progress` is called at each poll.
+pub async fn wait(
+    client: &EthersClient,
+    network: &Network,
+    tx_hash: H256,
+    from: Address,
+    tx_nonce: U256,
+    mut on_progress: impl FnMut(Progress),
+) -> Result<Outcome> {
+    let required = confirmations(network);
+    let deadline = Instant::now() + timeout(network);
+    loop {
+        let receipt = client
+            .get_transaction_receipt(tx_hash)
+            .await
+            .map_err(|e| eyre::eyre!("Failed to fetch the receipt: {}", e))?;
+        let latest = client
+            .get_block_number()
+            .await
+            .map_err(|e| eyre::eyre!("Failed to fetch block number: {}", e))?;
+        let mined_in = receipt
+            .as_ref()
+            .and_then(|r| r.block_number)
+            .map(|b| b.as_u64());
+        let account_nonce = if mined_in.is_some() {
+            U256::zero()
+        } else {
+            client
+                .get_transaction_count(from, Some(latest.into()))
+                .await
+                .map_err(|e| eyre::eyre!("Failed to fetch nonce: {}", e))?
+        };
+        let progress = Progress::new(
+            mined_in,
+            latest.as_u64(),
+            account_nonce,
+            tx_nonce,
+            required,
+        );
+        on_progress(progress);
+        // the receipt could have been mined right after the first check, so
+        // it is fetched again before reporting the transaction as replaced.
+        let recheck = match progress {
+            Progress::Replaced => {
+                Some(client.get_transaction_receipt(tx_hash).await.map_err(
+                    |e| eyre::eyre!("Failed to fetch the receipt: {}", e),
+                )?)
+            }
+            _ => None,
+        };
+        if let Some(outcome) = outcome(progress, receipt, recheck) {
+            return Ok(outcome);
+        }
+        if Instant::now() >= deadline {
+            return Ok(Outcome::TimedOut);
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// The outcome of a poll, `None` to keep polling.
+///
+/// `recheck` is the receipt fetched again when the transaction looked
+/// replaced, if it was mined in the meantime the next poll confirms it.
+fn outcome(
+    progress: Progress,
+    receipt: Option<TransactionReceipt>,
+    recheck: Option<Option<TransactionReceipt>>,
+) -> Option<Outcome> {
+    match (progress, receipt, recheck) {
+        (Progress::Confirmed, Some(receipt), _) => {
+            Some(Outcome::Mined(Box::new(receipt)))
+        }
+        (Progress::Replaced, _, Some(None)) => Some(Outcome::Replaced),
+        _ => None,
+    }
+}
+
+/// Describes whether the block is behind the `safe` and `finalized` heads,
+/// nodes that do not support these tags are ignored.
+pub async fn finality(client: &EthersClient, block: U64) -> Vec<String> {
+    let mut lines = Vec::new();
+    for (tag, name) in [
+        (BlockNumber::Safe, "safe"),
+        (BlockNumber::Finalized, "finalized"),
+    ] {
+        let head = match client.get_block(tag).await {
+            Ok(Some(head)) => head.number,
+            _ => None,
+        };
+        match head {
+            Some(head) if head >= block => {
+                lines.push(format!("Block {block} is {name}"));
+            }
+            Some(head) => lines.push(format!(
+                "Block {block} is not {name} yet, the {name} head is {head}"
+            )),
+            None => {}
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress() {
+        let nonce = U256::from(5);
+        assert_eq!(
+            Progress::new(None, 100, nonce, nonce, 3),
+            Progress::Pending
+        );
+        assert_eq!(
+            Progress::new(None, 100, nonce + 1, nonce, 3),
+            Progress::Replaced
+        );
+        assert_eq!(
+            Progress::new(Some(99), 100, nonce + 1, nonce, 3),
+            Progress::Confirming(2)
+        );
+        assert_eq!(
+            Progress::new(Some(98), 100, nonce + 1, nonce, 3),
+            Progress::Confirmed
+        );
+    }
+
+    #[test]
+    fn replaced_then_mined() {
+        let receipt = TransactionReceipt::default();
+        assert!(matches!(
+            outcome(Progress::Replaced, None, Some(None)),
+            Some(Outcome::Replaced)
+        ));
+        // mined between the two checks, the next poll confirms it.
+        assert!(
+            outcome(Progress::Replaced, None, Some(Some(receipt.clone())))
+                .is_none()
+        );
+        assert!(matches!(
+            outcome(Progress::Confirmed, Some(receipt.clone()), None),
+            Some(Outcome::Mined(_))
+        ));
+        assert!(outcome(Progress::Confirming(1), Some(receipt), None).is_none());
+        assert!(outcome(Progress::Pending, None, None).is_none());
+    }
+
+    #[test]
+    fn timeout_at_least_a_poll() {
+        let mut network = crate::config::Config::default()
+            .networks
+            .remove("mainnet")
+            .unwrap();
+        network.confirmation_timeout = Some(0);
+        assert_eq!(timeout(&network), POLL_INTERVAL);
+        network.confirmation_timeout = None;
+        assert_eq!(timeout(&network), Duration::from_secs(600));
+    }
+}
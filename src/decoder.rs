@@ -0,0 +1,421 @@
+//! Offline calldata decoding, used to show what a transaction is going to
+//! do in a human readable form before signing it.
+
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+
+use ethers::abi::{Abi, Function, HumanReadableParser, ParamType, Token};
+use ethers::types::Address;
+
+use crate::abi::{display_function, display_token};
+use crate::config::ContractConfig;
+use crate::price::Price;
+use crate::state::EthersClient;
+
+/// Well known function signatures, grouped by the contract that defines
+/// them. The standard token interfaces are taken from our own bindings.
+const SIGNATURES: &[(Interface, &[&str])] = &[
+    (
+        Interface::Multicall3,
+        &[
+            "function aggregate((address,bytes)[] calls) payable returns (uint256 blockNumber, bytes[] returnData)",
+            "function tryAggregate(bool requireSuccess, (address,bytes)[] calls) payable returns ((bool,bytes)[] returnData)",
+            "function blockAndAggregate((address,bytes)[] calls) payable returns (uint256 blockNumber, bytes32 blockHash, (bool,bytes)[] returnData)",
+            "function tryBlockAndAggregate(bool requireSuccess, (address,bytes)[] calls) payable returns (uint256 blockNumber, bytes32 blockHash, (bool,bytes)[] returnData)",
+            "function aggregate3((address,bool,bytes)[] calls) payable returns ((bool,bytes)[] returnData)",
+            "function aggregate3Value((address,bool,uint256,bytes)[] calls) payable returns ((bool,bytes)[] returnData)",
+        ],
+    ),
+    (
+        Interface::Multicall,
+        &[
+            "function multicall(bytes[] data) payable returns (bytes[] results)",
+            "function multicall(uint256 deadline, bytes[] data) payable returns (bytes[] results)",
+        ],
+    ),
+    (
+        Interface::Eip2612,
+        &[
+            "function permit(address owner, address spender, uint256 value, uint256 deadline, uint8 v, bytes32 r, bytes32 s)",
+            "function increaseAllowance(address spender, uint256 addedValue) returns (bool)",
+            "function decreaseAllowance(address spender, uint256 subtractedValue) returns (bool)",
+        ],
+    ),
+    (
+        Interface::UniswapV2Router,
+        &[
+            "function swapExactETHForTokens(uint256 amountOutMin, address[] path, address to, uint256 deadline) payable returns (uint256[] amounts)",
+            "function swapETHForExactTokens(uint256 amountOut, address[] path, address to, uint256 deadline) payable returns (uint256[] amounts)",
+            "function swapExactTokensForETH(uint256 amountIn, uint256 amountOutMin, address[] path, address to, uint256 deadline) returns (uint256[] amounts)",
+            "function swapTokensForExactETH(uint256 amountOut, uint256 amountInMax, address[] path, address to, uint256 deadline) returns (uint256[] amounts)",
+            "function swapExactTokensForTokens(uint256 amountIn, uint256 amountOutMin, address[] path, address to, uint256 deadline) returns (uint256[] amounts)",
+            "function swapTokensForExactTokens(uint256 amountOut, uint256 amountInMax, address[] path, address to, uint256 deadline) returns (uint256[] amounts)",
+            "function addLiquidity(address tokenA, address tokenB, uint256 amountADesired, uint256 amountBDesired, uint256 amountAMin, uint256 amountBMin, address to, uint256 deadline) returns (uint256 amountA, uint256 amountB, uint256 liquidity)",
+            "function addLiquidityETH(address token, uint256 amountTokenDesired, uint256 amountTokenMin, uint256 amountETHMin, address to, uint256 deadline) payable returns (uint256 amountToken, uint256 amountETH, uint256 liquidity)",
+            "function removeLiquidity(address tokenA, address tokenB, uint256 liquidity, uint256 amountAMin, uint256 amountBMin, address to, uint256 deadline) returns (uint256 amountA, uint256 amountB)",
+            "function removeLiquidityETH(address token, uint256 liquidity, uint256 amountTokenMin, uint256 amountETHMin, address to, uint256 deadline) returns (uint256 amountToken, uint256 amountETH)",
+        ],
+    ),
+    (
+        Interface::UniswapV3Router,
+        &[
+            "function exactInputSingle((address,address,uint24,address,uint256,uint256,uint256,uint160) params) payable returns (uint256 amountOut)",
+            "function exactInput((bytes,address,uint256,uint256,uint256) params) payable returns (uint256 amountOut)",
+            "function exactOutputSingle((address,address,uint24,address,uint256,uint256,uint256,uint160) params) payable returns (uint256 amountIn)",
+            "function exactOutput((bytes,address,uint256,uint256,uint256) params) payable returns (uint256 amountIn)",
+            "function unwrapWETH9(uint256 amountMinimum, address recipient) payable",
+            "function refundETH() payable",
+            "function sweepToken(address token, uint256 amountMinimum, address recipient) payable",
+        ],
+    ),
+    (
+        Interface::Permit2,
+        &[
+            "function approve(address token, address spender, uint160 amount, uint48 expiration)",
+            "function lockdown((address,address)[] approvals)",
+            "function invalidateNonces(address token, address spender, uint48 newNonce)",
+        ],
+    ),
+];
+
+/// The well known interfaces whose functions are decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interface {
+    Erc20,
+    Erc721,
+    Erc1155,
+    Weth,
+    Multicall3,
+    Multicall,
+    Eip2612,
+    UniswapV2Router,
+    UniswapV3Router,
+    Permit2,
+}
+
+impl std::fmt::Display for Interface {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Erc20 => write!(f, "ERC20"),
+            Self::Erc721 => write!(f, "ERC721"),
+            Self::Erc1155 => write!(f, "ERC1155"),
+            Self::Weth => write!(f, "WETH"),
+            Self::Multicall3 => write!(f, "Multicall3"),
+            Self::Multicall => write!(f, "Multicall"),
+            Self::Eip2612 => write!(f, "EIP-2612"),
+            Self::UniswapV2Router => write!(f, "Uniswap V2 Router"),
+            Self::UniswapV3Router => write!(f, "Uniswap V3 Router"),
+            Self::Permit2 => write!(f, "Permit2"),
+        }
+    }
+}
+
+/// Where the decoded function comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// The ABI of the configured contract with this name.
+    Contract(String),
+    /// The well known interfaces that define this function, e.g. ERC20 and
+    /// ERC721.
+    Interfaces(Vec<Interface>),
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Contract(name) => write!(f, "{}", name),
+            Self::Interfaces(interfaces) => {
+                let names = interfaces
+                    .iter()
+                    .map(|i| i.to_string())
+                    .collect::<Vec<_>>();
+                write!(f, "{}", names.join(", "))
+            }
+        }
+    }
+}
+
+/// A decoded function call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedCall {
+    pub source: Source,
+    pub function: Function,
+    pub args: Vec<Token>,
+}
+
+/// The token used to format the amounts of a decoded call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenInfo {
+    pub symbol: String,
+    pub decimals: u8,
+}
+
+impl TokenInfo {
+    /// Fetch the token symbol and decimals, `None` if the contract is not
+    /// a fungible token.
+    pub async fn fetch(client: &EthersClient, token: Address) -> Option<Self> {
+        let contract =
+            crate::erc20::Erc20::new(token, Arc::new(client.clone()));
+        let decimals = contract.decimals().call().await.ok()?;
+        let symbol = contract
+            .symbol()
+            .call()
+            .await
+            .unwrap_or_else(|_| ethers::utils::to_checksum(&token, None));
+        Some(Self { symbol, decimals })
+    }
+}
+
+type KnownFunctions = HashMap<[u8; 4], (Vec<Interface>, Function)>;
+
+fn known_functions() -> &'static KnownFunctions {
+    static FUNCTIONS: OnceLock<KnownFunctions> = OnceLock::new();
+    FUNCTIONS.get_or_init(|| {
+        let mut functions = HashMap::new();
+        let mut insert = |source: Interface, function: Function| {
+            functions
+                .entry(function.short_signature())
+                .and_modify(|(sources, _): &mut (Vec<Interface>, Function)| {
+                    if !sources.contains(&source) {
+                        sources.push(source);
+                    }
+                })
+                .or_insert_with(|| (vec![source], function));
+        };
+        let bindings: [(Interface, &Abi); 4] = [
+            (Interface::Erc20, &crate::erc20::ERC20_ABI),
+            (Interface::Erc721, &crate::erc721::ERC721_ABI),
+            (Interface::Erc1155, &crate::erc1155::ERC1155_ABI),
+            (Interface::Weth, &crate::weth::WETH_ABI),
+        ];
+        for (source, abi) in bindings {
+            for function in abi.functions() {
+                insert(source, function.clone());
+            }
+        }
+        for (source, signatures) in SIGNATURES {
+            for signature in *signatures {
+                let function = HumanReadableParser::parse_function(signature)
+                    .expect("valid function signature");
+                insert(*source, function);
+            }
+        }
+        functions
+    })
+}
+
+/// Decode the calldata, using the ABI of the configured contract first (if
+/// any), then the bundled signatures.
+pub fn decode(
+    calldata: &[u8],
+    contract: Option<&ContractConfig>,
+) -> Option<DecodedCall> {
+    if calldata.len() < 4 {
+        return None;
+    }
+    let (selector, data) = calldata.split_at(4);
+    let from_contract = contract.and_then(|contract| {
+        let function = contract
+            .abi
+            .functions()
+            .find(|f| f.short_signature() == selector)?;
+        Some((Source::Contract(contract.name.clone()), function.clone()))
+    });
+    let (source, function) = match from_contract {
+        Some(found) => found,
+        None => {
+            let (interfaces, function) = known_functions().get(selector)?;
+            (Source::Interfaces(interfaces.clone()), function.clone())
+        }
+    };
+    let args = function.decode_input(data).ok()?;
+    Some(DecodedCall {
+        source,
+        function,
+        args,
+    })
+}
+
+impl DecodedCall {
+    /// Returns `true` if the call is defined by a fungible token interface,
+    /// hence its amounts could be formatted using the token decimals.
+    pub fn is_token_call(&self) -> bool {
+        [Interface::Erc20, Interface::Weth, Interface::Eip2612]
+            .into_iter()
+            .any(|interface| self.is_defined_by(interface))
+    }
+
+    /// Returns `true` if the function is part of the well known interface.
+    pub fn is_defined_by(&self, interface: Interface) -> bool {
+        match &self.source {
+            Source::Interfaces(interfaces) => interfaces.contains(&interface),
+            Source::Contract(_) => false,
+        }
+    }
+
+    /// Human readable lines of the call and its arguments, nested calls
+    /// (e.g. multicalls) are decoded and indented under their argument.
+    ///
+    /// The amounts are formatted using the token info, with their USD value
+    /// if the token `price` is known.
+    pub fn lines(
+        &self,
+        token: Option<&TokenInfo>,
+        price: Option<&Price>,
+    ) -> Vec<String> {
+        let mut lines = vec![format!(
+            "{} [{}]",
+            display_function(&self.function),
+            self.source
+        )];
+        for (i, (param, arg)) in
+            self.function.inputs.iter().zip(&self.args).enumerate()
+        {
+            let name = if param.name.is_empty() {
+                format!("arg{i}")
+            } else {
+                param.name.clone()
+            };
+            let value = match token {
+                Some(token) if is_amount(&name, &param.kind) => {
+                    display_amount(arg, token, price)
+                }
+                _ => display_token(arg),
+            };
+            lines.push(format!("  {name}: {value}"));
+            for nested in nested_calls(arg) {
+                lines.extend(
+                    nested
+                        .lines(None, None)
+                        .into_iter()
+                        .map(|l| format!("    {l}")),
+                );
+            }
+        }
+        lines
+    }
+}
+
+fn is_amount(name: &str, kind: &ParamType) -> bool {
+    matches!(kind, ParamType::Uint(_))
+        && matches!(
+            name,
+            "amount" | "value" | "wad" | "addedValue" | "subtractedValue"
+        )
+}
+
+fn display_amount(
+    arg: &Token,
+    token: &TokenInfo,
+    price: Option<&Price>,
+) -> String {
+    match arg {
+        Token::Uint(amount) => {
+            match ethers::utils::format_units(*amount, token.decimals as u32) {
+                Ok(formatted) => {
+                    let usd = price
+                        .map(|p| p.suffix(*amount, token.decimals))
+                        .unwrap_or_default();
+                    format!(
+                        "{} {}{} ({})",
+                        formatted, token.symbol, usd, amount
+                    )
+                }
+                Err(_) => amount.to_string(),
+            }
+        }
+        _ => display_token(arg),
+    }
+}
+
+/// Any bytes inside the argument that are themselves a known call.
+fn nested_calls(arg: &Token) -> Vec<DecodedCall> {
+    match arg {
+        Token::Bytes(bytes) => decode(bytes, None).into_iter().collect(),
+        Token::Array(tokens)
+        | Token::FixedArray(tokens)
+        | Token::Tuple(tokens) => {
+            tokens.iter().flat_map(nested_calls).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::abi::AbiEncode;
+    use ethers::types::{Address, U256};
+
+    use super::*;
+
+    #[test]
+    fn decode_erc20_transfer() {
+        let calldata = crate::erc20::TransferCall {
+            recipient: Address::repeat_byte(1),
+            amount: U256::from(1_500_000),
+        }
+        .encode();
+        let call = decode(&calldata, None).unwrap();
+        assert_eq!(call.function.name, "transfer");
+        assert!(call.is_token_call());
+        let token = TokenInfo {
+            symbol: "USDC".into(),
+            decimals: 6,
+        };
+        let lines = call.lines(Some(&token), None);
+        assert_eq!(
+            lines[0],
+            "transfer(address recipient, uint256 amount) [ERC20]"
+        );
+        assert_eq!(lines[2], "  amount: 1.500000 USDC (1500000)");
+        let price = Price {
+            answer: U256::from(100_000_000),
+            decimals: 8,
+            updated_at: crate::policy::now(),
+        };
+        let lines = call.lines(Some(&token), Some(&price));
+        assert_eq!(lines[2], "  amount: 1.500000 USDC (~$1.50) (1500000)");
+    }
+
+    #[test]
+    fn shared_selectors() {
+        let calldata = crate::erc721::TransferFromCall {
+            from: Address::repeat_byte(1),
+            to: Address::repeat_byte(2),
+            token_id: U256::from(7),
+        }
+        .encode();
+        let call = decode(&calldata, None).unwrap();
+        assert_eq!(
+            call.source,
+            Source::Interfaces(vec![Interface::Erc20, Interface::Erc721])
+        );
+        assert_eq!(call.source.to_string(), "ERC20, ERC721");
+    }
+
+    #[test]
+    fn decode_nested_multicall() {
+        let inner = crate::weth::WithdrawCall { wad: U256::from(1) }.encode();
+        let multicall = HumanReadableParser::parse_function(
+            "function aggregate((address,bytes)[] calls)",
+        )
+        .unwrap();
+        let calldata = multicall
+            .encode_input(&[Token::Array(vec![Token::Tuple(vec![
+                Token::Address(Address::repeat_byte(3)),
+                Token::Bytes(inner),
+            ])])])
+            .unwrap();
+        let call = decode(&calldata, None).unwrap();
+        assert_eq!(call.source.to_string(), "Multicall3");
+        let lines = call.lines(None, None);
+        assert_eq!(lines[2], "    withdraw(uint256 wad) [WETH]");
+        assert_eq!(lines[3], "      wad: 1");
+    }
+
+    #[test]
+    fn unknown_selector() {
+        assert!(decode(&[0xde, 0xad, 0xbe, 0xef], None).is_none());
+        assert!(decode(&[0xde, 0xad], None).is_none());
+    }
+}
@@ -0,0 +1,185 @@
+//! EIP-712 typed structured data helpers.
+
+use color_eyre::eyre::{self, Context};
+use color_eyre::Result;
+use ethers::types::transaction::eip712::{Eip712, TypedData, Types};
+use ethers::types::H256;
+use serde_json::Value;
+
+/// Parse and validate an EIP-712 JSON payload.
+///
+/// The payload is considered valid only if it could be hashed, so any
+/// missing types or invalid values are reported here.
+pub fn parse(json: &str) -> Result<TypedData> {
+    let typed_data: TypedData =
+        serde_json::from_str(json).context("Invalid EIP-712 payload")?;
+    hash(&typed_data)
+        .map_err(|e| eyre::eyre!("Invalid EIP-712 payload: {}", e))?;
+    Ok(typed_data)
+}
+
+/// The EIP-712 hash of the typed data, this what actually gets signed.
+pub fn hash(typed_data: &TypedData) -> Result<H256> {
+    let hash = typed_data.encode_eip712().map_err(|e| eyre::eyre!(e))?;
+    Ok(H256::from(hash))
+}
+
+/// Human readable lines describing the signing domain.
+pub fn domain_lines(typed_data: &TypedData) -> Vec<String> {
+    let domain = &typed_data.domain;
+    let mut lines = Vec::new();
+    if let Some(name) = &domain.name {
+        lines.push(format!("  name: {name}"));
+    }
+    if let Some(version) = &domain.version {
+        lines.push(format!("  version: {version}"));
+    }
+    if let Some(chain_id) = &domain.chain_id {
+        lines.push(format!("  chainId: {chain_id}"));
+    }
+    if let Some(contract) = &domain.verifying_contract {
+        lines.push(format!("  verifyingContract: {contract:?}"));
+    }
+    if let Some(salt) = &domain.salt {
+        lines.push(format!("  salt: 0x{}", hex::encode(salt)));
+    }
+    lines
+}
+
+/// Human readable tree of the message fields, following the declared types
+/// starting from the primary type.
+pub fn field_tree(typed_data: &TypedData) -> Vec<String> {
+    let message =
+        Value::Object(typed_data.message.clone().into_iter().collect());
+    let mut lines = Vec::new();
+    push_struct(
+        &typed_data.types,
+        &typed_data.primary_type,
+        &message,
+        1,
+        &mut lines,
+    );
+    lines
+}
+
+fn push_struct(
+    types: &Types,
+    ty: &str,
+    value: &Value,
+    depth: usize,
+    lines: &mut Vec<String>,
+) {
+    for field in types.get(ty).into_iter().flatten() {
+        let value = value.get(&field.name).unwrap_or(&Value::Null);
+        push_field(types, &field.name, &field.r#type, value, depth, lines);
+    }
+}
+
+fn push_field(
+    types: &Types,
+    name: &str,
+    ty: &str,
+    value: &Value,
+    depth: usize,
+    lines: &mut Vec<String>,
+) {
+    let indent = "  ".repeat(depth);
+    if let Some(item_ty) = array_item_type(ty) {
+        lines.push(format!("{indent}{name} ({ty}):"));
+        for (i, item) in value.as_array().into_iter().flatten().enumerate() {
+            push_field(
+                types,
+                &format!("[{i}]"),
+                item_ty,
+                item,
+                depth + 1,
+                lines,
+            );
+        }
+    } else if types.contains_key(ty) {
+        lines.push(format!("{indent}{name} ({ty}):"));
+        push_struct(types, ty, value, depth + 1, lines);
+    } else {
+        let value = match value {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        lines.push(format!("{indent}{name} ({ty}): {value}"));
+    }
+}
+
+/// Returns the item type if the type is an array, i.e. `Person[]` or
+/// `uint256[2]`.
+fn array_item_type(ty: &str) -> Option<&str> {
+    let ty = ty.strip_suffix(']')?;
+    let (item_ty, _) = ty.rsplit_once('[')?;
+    Some(item_ty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAIL: &str = r#"{
+        "types": {
+            "EIP712Domain": [
+                { "name": "name", "type": "string" },
+                { "name": "version", "type": "string" },
+                { "name": "chainId", "type": "uint256" },
+                { "name": "verifyingContract", "type": "address" }
+            ],
+            "Person": [
+                { "name": "name", "type": "string" },
+                { "name": "wallet", "type": "address" }
+            ],
+            "Mail": [
+                { "name": "from", "type": "Person" },
+                { "name": "to", "type": "Person[]" },
+                { "name": "contents", "type": "string" }
+            ]
+        },
+        "primaryType": "Mail",
+        "domain": {
+            "name": "Ether Mail",
+            "version": "1",
+            "chainId": 1,
+            "verifyingContract": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"
+        },
+        "message": {
+            "from": {
+                "name": "Cow",
+                "wallet": "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"
+            },
+            "to": [{
+                "name": "Bob",
+                "wallet": "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"
+            }],
+            "contents": "Hello, Bob!"
+        }
+    }"#;
+
+    #[test]
+    fn mail_field_tree() {
+        let typed_data = parse(MAIL).unwrap();
+        assert_eq!(
+            field_tree(&typed_data),
+            vec![
+                "  from (Person):",
+                "    name (string): Cow",
+                "    wallet (address): 0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826",
+                "  to (Person[]):",
+                "    [0] (Person):",
+                "      name (string): Bob",
+                "      wallet (address): 0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB",
+                "  contents (string): Hello, Bob!",
+            ]
+        );
+    }
+
+    #[test]
+    fn reject_missing_types() {
+        let json = MAIL
+            .replace(r#""primaryType": "Mail""#, r#""primaryType": "Letter""#);
+        assert!(parse(&json).is_err());
+    }
+}
@@ -0,0 +1,109 @@
+//! Passphrase encrypted files, for the local data that should not reveal
+//! the wallet activity to anyone else with access to the disk.
+//!
+//! The key is derived from the passphrase using scrypt, and the content is
+//! encrypted using ChaCha20-Poly1305. The file layout is
+//! `salt (16 bytes) || nonce (12 bytes) || ciphertext`.
+
+use std::path::Path;
+
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use color_eyre::eyre;
+use color_eyre::Result;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Load and decrypt the file, `None` if it does not exist yet.
+pub fn load<T: DeserializeOwned>(
+    path: impl AsRef<Path>,
+    passphrase: &str,
+) -> Result<Option<T>> {
+    let path = path.as_ref();
+    if !path.exists() {
+        return Ok(None);
+    }
+    let data = std::fs::read(path)?;
+    let plaintext = decrypt(&data, passphrase, &scrypt::Params::recommended())
+        .map_err(|e| eyre::eyre!("Failed to read {}: {}", path.display(), e))?;
+    let value = serde_json::from_slice(&plaintext)?;
+    Ok(Some(value))
+}
+
+/// Encrypt and save the value to the file, replacing it.
+pub fn save<T: Serialize>(
+    path: impl AsRef<Path>,
+    passphrase: &str,
+    value: &T,
+) -> Result<()> {
+    let plaintext = serde_json::to_vec(value)?;
+    let data = encrypt(&plaintext, passphrase, &scrypt::Params::recommended())?;
+    std::fs::write(path, data)?;
+    Ok(())
+}
+
+fn derive_key(
+    passphrase: &str,
+    salt: &[u8],
+    params: &scrypt::Params,
+) -> Result<Key> {
+    let mut key = Key::default();
+    scrypt::scrypt(passphrase.as_bytes(), salt, params, &mut key)
+        .map_err(|e| eyre::eyre!("Failed to derive the key: {}", e))?;
+    Ok(key)
+}
+
+fn encrypt(
+    plaintext: &[u8],
+    passphrase: &str,
+    params: &scrypt::Params,
+) -> Result<Vec<u8>> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let key = derive_key(passphrase, &salt, params)?;
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = ChaCha20Poly1305::new(&key)
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| eyre::eyre!("Failed to encrypt"))?;
+    Ok([&salt[..], &nonce[..], &ciphertext[..]].concat())
+}
+
+fn decrypt(
+    data: &[u8],
+    passphrase: &str,
+    params: &scrypt::Params,
+) -> Result<Vec<u8>> {
+    if data.len() < SALT_LEN + NONCE_LEN {
+        eyre::bail!("The file is corrupted");
+    }
+    let (salt, data) = data.split_at(SALT_LEN);
+    let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+    let key = derive_key(passphrase, salt, params)?;
+    ChaCha20Poly1305::new(&key)
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| eyre::eyre!("Wrong passphrase or corrupted file"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params() -> scrypt::Params {
+        // fast parameters, the recommended ones are slow in debug builds.
+        scrypt::Params::new(4, 8, 1).unwrap()
+    }
+
+    #[test]
+    fn encrypt_decrypt() {
+        let data = encrypt(b"hello", "passphrase", &params()).unwrap();
+        assert_ne!(&data[SALT_LEN + NONCE_LEN..], b"hello");
+        let plaintext = decrypt(&data, "passphrase", &params()).unwrap();
+        assert_eq!(plaintext, b"hello");
+        assert!(decrypt(&data, "wrong", &params()).is_err());
+        assert!(decrypt(&data[..10], "passphrase", &params()).is_err());
+    }
+}
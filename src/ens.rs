@@ -0,0 +1,122 @@
+//! ENS names resolution, using the registry configured for the network.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use color_eyre::eyre;
+use color_eyre::Result;
+use ethers::providers::{Middleware, ProviderError};
+use ethers::types::Address;
+
+use crate::state::EthersClient;
+
+/// Returns `true` if the input looks like an ENS name, e.g. `vitalik.eth`.
+pub fn is_name(s: &str) -> bool {
+    let s = s.trim();
+    !s.starts_with("0x")
+        && !s.contains(char::is_whitespace)
+        && s.split('.').count() > 1
+        && s.split('.').all(|label| !label.is_empty())
+}
+
+/// Resolves the ENS name to its address.
+pub async fn resolve(client: &EthersClient, name: &str) -> Result<Address> {
+    let address = client
+        .resolve_name(name.trim())
+        .await
+        .map_err(|e| eyre::eyre!("Failed to resolve {}: {}", name, e))?;
+    if address.is_zero() {
+        eyre::bail!("{} does not resolve to any address", name);
+    }
+    Ok(address)
+}
+
+/// Looks up the primary ENS name of the address, the name is verified to
+/// resolve back to the same address.
+///
+/// The results are cached for the session, a wallet only runs on a single
+/// network at a time, the failed lookups are tried again.
+pub async fn lookup(client: &EthersClient, address: Address) -> Option<String> {
+    if let Some(name) = cache().lock().ok()?.get(&address) {
+        return name.clone();
+    }
+    let name = confirmed(client.lookup_address(address).await)?;
+    cache().lock().ok()?.insert(address, name.clone());
+    name
+}
+
+/// The looked up name, `Some(None)` if the address has no verified name and
+/// `None` if the lookup failed (e.g. the node is unreachable).
+fn confirmed(
+    result: std::result::Result<String, ProviderError>,
+) -> Option<Option<String>> {
+    match result {
+        Ok(name) => Some(Some(name)),
+        // no reverse record, or it does not resolve back to the address.
+        Err(ProviderError::EnsError(_) | ProviderError::EnsNotOwned(_)) => {
+            Some(None)
+        }
+        Err(_) => None,
+    }
+}
+
+fn cache() -> &'static Mutex<HashMap<Address, Option<String>>> {
+    static CACHE: OnceLock<Mutex<HashMap<Address, Option<String>>>> =
+        OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+/// The address, followed by its ENS name if any.
+pub fn label(address: Address, name: Option<&str>) -> String {
+    let address = ethers::utils::to_checksum(&address, None);
+    match name {
+        Some(name) => format!("{address} ({name})"),
+        None => address,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ens_names() {
+        assert!(is_name("vitalik.eth"));
+        assert!(is_name("pay.vitalik.eth "));
+        assert!(!is_name("vitalik"));
+        assert!(!is_name("vitalik..eth"));
+        assert!(!is_name("0x0000000000000000000000000000000000000000"));
+        assert!(!is_name(
+            "Burn | 0x0000000000000000000000000000000000000000"
+        ));
+    }
+
+    #[test]
+    fn lookup_results() {
+        assert_eq!(
+            confirmed(Ok("test.eth".into())),
+            Some(Some("test.eth".into()))
+        );
+        let no_record = ProviderError::EnsError("test.eth".into());
+        assert_eq!(confirmed(Err(no_record)), Some(None));
+        let not_owned = ProviderError::EnsNotOwned("test.eth".into());
+        assert_eq!(confirmed(Err(not_owned)), Some(None));
+        let unreachable = ProviderError::CustomError("timeout".into());
+        assert_eq!(confirmed(Err(unreachable)), None);
+    }
+
+    #[test]
+    fn labels() {
+        let address: Address = "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            label(address, Some("test.eth")),
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed (test.eth)"
+        );
+        assert_eq!(
+            label(address, None),
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+        );
+    }
+}
@@ -0,0 +1,23 @@
+use ethers::contract::abigen;
+
+abigen!(
+    Erc721,
+    r#"[
+        function name() external view returns (string memory)
+        function symbol() external view returns (string memory)
+        function tokenURI(uint256 tokenId) external view returns (string memory)
+        function balanceOf(address owner) external view returns (uint256)
+        function ownerOf(uint256 tokenId) external view returns (address)
+        function safeTransferFrom(address from, address to, uint256 tokenId) external
+        function transferFrom(address from, address to, uint256 tokenId) external
+        function approve(address to, uint256 tokenId) external
+        function setApprovalForAll(address operator, bool approved) external
+        function getApproved(uint256 tokenId) external view returns (address)
+        function isApprovedForAll(address owner, address operator) external view returns (bool)
+        function supportsInterface(bytes4 interfaceId) external view returns (bool)
+        event Transfer(address indexed from, address indexed to, uint256 indexed tokenId)
+        event Approval(address indexed owner, address indexed approved, uint256 indexed tokenId)
+        event ApprovalForAll(address indexed owner, address indexed operator, bool approved)
+    ]"#,
+    event_derives(serde::Deserialize, serde::Serialize),
+);
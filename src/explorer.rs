@@ -0,0 +1,325 @@
+//! Transactions history from an Etherscan compatible explorer API, e.g.
+//! the Etherscan V2 API which serves every chain from the same URL.
+
+use color_eyre::eyre;
+use color_eyre::Result;
+use ethers::types::{Address, H256, U256};
+use serde::Deserialize;
+
+use crate::history::{Direction, HistoryEntry};
+
+/// Number of transactions per page.
+pub const PAGE_SIZE: usize = 25;
+
+/// Number of transactions per request when fetching the whole history, the
+/// APIs only return the first 10000 transactions.
+const EXPORT_PAGE_SIZE: usize = 1_000;
+const MAX_TRANSACTIONS: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryKind {
+    /// The transactions sent or received by the account.
+    Transactions,
+    /// The ERC20 token transfers from or to the account.
+    TokenTransfers,
+}
+
+impl HistoryKind {
+    fn action(&self) -> &'static str {
+        match self {
+            Self::Transactions => "txlist",
+            Self::TokenTransfers => "tokentx",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Explorer {
+    client: reqwest::Client,
+    api_url: url::Url,
+    api_key: Option<String>,
+    chain_id: U256,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiResponse {
+    message: String,
+    result: serde_json::Value,
+}
+
+/// A transaction or a token transfer, numbers are returned as strings.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiTransaction {
+    time_stamp: String,
+    hash: H256,
+    from: Address,
+    /// Empty for contract creations.
+    to: String,
+    value: String,
+    #[serde(default)]
+    gas_price: String,
+    #[serde(default)]
+    gas_used: String,
+    #[serde(default)]
+    is_error: Option<String>,
+    #[serde(default)]
+    contract_address: String,
+    #[serde(default)]
+    token_symbol: Option<String>,
+    #[serde(default)]
+    token_decimal: Option<String>,
+    /// Only returned for the token transfers, by some explorers.
+    #[serde(default)]
+    log_index: Option<String>,
+}
+
+impl Explorer {
+    pub fn new(
+        client: reqwest::Client,
+        api_url: url::Url,
+        api_key: Option<String>,
+        chain_id: U256,
+    ) -> Self {
+        Self {
+            client,
+            api_url,
+            api_key,
+            chain_id,
+        }
+    }
+
+    /// Fetches a page, starting from 1, of the account history, the most
+    /// recent first.
+    pub async fn history(
+        &self,
+        kind: HistoryKind,
+        account: Address,
+        currency_symbol: &str,
+        page: usize,
+    ) -> Result<Vec<HistoryEntry>> {
+        self.fetch(kind, account, currency_symbol, page, PAGE_SIZE)
+            .await
+    }
+
+    /// Fetches the whole account history, up to the API limit.
+    pub async fn all(
+        &self,
+        kind: HistoryKind,
+        account: Address,
+        currency_symbol: &str,
+    ) -> Result<Vec<HistoryEntry>> {
+        let mut entries = Vec::new();
+        for page in 1..=MAX_TRANSACTIONS / EXPORT_PAGE_SIZE {
+            let page_entries = self
+                .fetch(kind, account, currency_symbol, page, EXPORT_PAGE_SIZE)
+                .await?;
+            let last_page = page_entries.len() < EXPORT_PAGE_SIZE;
+            entries.extend(page_entries);
+            if last_page {
+                break;
+            }
+        }
+        Ok(entries)
+    }
+
+    async fn fetch(
+        &self,
+        kind: HistoryKind,
+        account: Address,
+        currency_symbol: &str,
+        page: usize,
+        page_size: usize,
+    ) -> Result<Vec<HistoryEntry>> {
+        let mut url = self.api_url.clone();
+        url.query_pairs_mut()
+            .append_pair("chainid", &self.chain_id.to_string())
+            .append_pair("module", "account")
+            .append_pair("action", kind.action())
+            .append_pair("address", &format!("{account:?}"))
+            .append_pair("page", &page.to_string())
+            .append_pair("offset", &page_size.to_string())
+            .append_pair("sort", "desc");
+        if let Some(api_key) = &self.api_key {
+            url.query_pairs_mut().append_pair("apikey", api_key);
+        }
+        let response =
+            self.client.get(url).send().await.map_err(|e| {
+                eyre::eyre!("Failed to fetch the history: {}", e)
+            })?;
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| eyre::eyre!("Failed to read the history: {}", e))?;
+        // the API error, e.g. a deprecated endpoint, could come with an
+        // error status.
+        let response = match serde_json::from_str::<ApiResponse>(&body) {
+            Ok(response) => response,
+            Err(_) if !status.is_success() => eyre::bail!(
+                "Failed to fetch the history: {} {}",
+                status,
+                body.trim()
+            ),
+            Err(e) => eyre::bail!("Failed to read the history: {}", e),
+        };
+        parse_response(response, account, currency_symbol)
+    }
+}
+
+fn parse_response(
+    response: ApiResponse,
+    account: Address,
+    currency_symbol: &str,
+) -> Result<Vec<HistoryEntry>> {
+    // errors are returned as a string result, e.g. an invalid API key.
+    let transactions: Vec<ApiTransaction> =
+        serde_json::from_value(response.result.clone()).map_err(|_| {
+            let result = match &response.result {
+                serde_json::Value::String(result) => result.clone(),
+                result => result.to_string(),
+            };
+            eyre::eyre!("Explorer API error: {} ({})", response.message, result)
+        })?;
+    transactions
+        .into_iter()
+        .map(|tx| tx.into_entry(account, currency_symbol))
+        .collect()
+}
+
+impl ApiTransaction {
+    fn into_entry(
+        self,
+        account: Address,
+        currency_symbol: &str,
+    ) -> Result<HistoryEntry> {
+        let to = if self.to.is_empty() {
+            None
+        } else {
+            Some(self.to.parse::<Address>()?)
+        };
+        let direction = Direction::new(account, self.from, to);
+        let counterparty = match direction {
+            Direction::In => Some(self.from),
+            Direction::Out | Direction::SelfTransfer => to,
+        };
+        let (token, symbol, decimals) = match self.token_symbol {
+            Some(symbol) => (
+                Some(self.contract_address.parse::<Address>()?),
+                symbol,
+                self.token_decimal.as_deref().unwrap_or("0").parse()?,
+            ),
+            None => (None, currency_symbol.to_string(), 18),
+        };
+        let fee = if self.from == account {
+            let gas_price = U256::from_dec_str(&self.gas_price).ok();
+            let gas_used = U256::from_dec_str(&self.gas_used).ok();
+            gas_price.zip(gas_used).map(|(p, u)| p.saturating_mul(u))
+        } else {
+            None
+        };
+        Ok(HistoryEntry {
+            timestamp: self.time_stamp.parse()?,
+            hash: self.hash,
+            log_index: match self.log_index {
+                Some(index) => Some(index.parse()?),
+                None => None,
+            },
+            direction,
+            counterparty,
+            token,
+            symbol,
+            amount: U256::from_dec_str(&self.value)?,
+            decimals,
+            fee,
+            failed: self.is_error.as_deref() == Some("1"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(value: serde_json::Value) -> ApiResponse {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn parse_transactions() {
+        let account = Address::repeat_byte(1);
+        let response = response(serde_json::json!({
+            "status": "1",
+            "message": "OK",
+            "result": [{
+                "blockNumber": "16000000",
+                "timeStamp": "1675259100",
+                "hash": format!("{:?}", H256::repeat_byte(3)),
+                "from": format!("{account:?}"),
+                "to": format!("{:?}", Address::repeat_byte(2)),
+                "value": "1500000000000000000",
+                "gasPrice": "10",
+                "gasUsed": "21000",
+                "isError": "0",
+                "contractAddress": ""
+            }, {
+                "timeStamp": "1675259000",
+                "hash": format!("{:?}", H256::repeat_byte(4)),
+                "from": format!("{:?}", Address::repeat_byte(2)),
+                "to": format!("{account:?}"),
+                "value": "1500000",
+                "gasPrice": "10",
+                "gasUsed": "50000",
+                "contractAddress": format!("{:?}", Address::repeat_byte(5)),
+                "tokenSymbol": "USDC",
+                "tokenDecimal": "6",
+                "logIndex": "12"
+            }]
+        }));
+        let entries = parse_response(response, account, "ETH").unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].direction, Direction::Out);
+        assert_eq!(entries[0].counterparty, Some(Address::repeat_byte(2)));
+        assert_eq!(entries[0].units(), "1.5");
+        assert_eq!(entries[0].symbol, "ETH");
+        assert_eq!(entries[0].fee, Some(U256::from(210_000)));
+        assert_eq!(entries[1].direction, Direction::In);
+        assert_eq!(entries[1].token, Some(Address::repeat_byte(5)));
+        assert_eq!(entries[1].units(), "1.5");
+        assert_eq!(entries[1].fee, None);
+        assert_eq!(entries[1].log_index, Some(12));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let empty = response(serde_json::json!({
+            "status": "0",
+            "message": "No transactions found",
+            "result": []
+        }));
+        assert!(parse_response(empty, Address::zero(), "ETH")
+            .unwrap()
+            .is_empty());
+        let invalid_key = response(serde_json::json!({
+            "status": "0",
+            "message": "NOTOK",
+            "result": "Invalid API Key"
+        }));
+        let err =
+            parse_response(invalid_key, Address::zero(), "ETH").unwrap_err();
+        assert!(err.to_string().contains("Invalid API Key"));
+        let deprecated = response(serde_json::json!({
+            "status": "0",
+            "message": "NOTOK",
+            "result": "You are using a deprecated V1 endpoint, switch to \
+                       Etherscan API V2"
+        }));
+        let err =
+            parse_response(deprecated, Address::zero(), "ETH").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Explorer API error: NOTOK (You are using a deprecated V1 \
+             endpoint, switch to Etherscan API V2)"
+        );
+    }
+}
@@ -0,0 +1,341 @@
+//! The account history, as a list of transfers of the native currency or
+//! of ERC20 tokens, whatever the source is.
+
+use std::collections::HashSet;
+
+use color_eyre::Result;
+use ethers::types::{Address, H256, U256};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Contact;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Direction {
+    In,
+    Out,
+    /// Sent to the account itself.
+    SelfTransfer,
+}
+
+impl Direction {
+    pub fn new(account: Address, from: Address, to: Option<Address>) -> Self {
+        match (from == account, to == Some(account)) {
+            (true, true) => Self::SelfTransfer,
+            (true, false) => Self::Out,
+            _ => Self::In,
+        }
+    }
+}
+
+impl std::fmt::Display for Direction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::In => write!(f, "IN"),
+            Self::Out => write!(f, "OUT"),
+            Self::SelfTransfer => write!(f, "SELF"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// UNIX timestamp, in seconds.
+    pub timestamp: u64,
+    pub hash: H256,
+    /// The index of the `Transfer` log in the block, `None` for the native
+    /// transfers.
+    #[serde(default)]
+    pub log_index: Option<u64>,
+    pub direction: Direction,
+    /// The other side of the transfer, `None` for contract creations.
+    pub counterparty: Option<Address>,
+    /// The ERC20 token contract, `None` for the native currency.
+    pub token: Option<Address>,
+    pub symbol: String,
+    pub amount: U256,
+    pub decimals: u8,
+    /// The fee paid by the account, in wei, if it sent the transaction.
+    pub fee: Option<U256>,
+    /// `true` if the transaction reverted.
+    pub failed: bool,
+}
+
+impl HistoryEntry {
+    /// The amount in token units, e.g. `1.5`.
+    pub fn units(&self) -> String {
+        format_units(self.amount, self.decimals)
+    }
+
+    /// The counterparty contact name, or its address.
+    pub fn counterparty_label(&self, contacts: &[Contact]) -> String {
+        match self.counterparty {
+            Some(address) => contacts
+                .iter()
+                .find(|c| c.address == address)
+                .map(|c| c.name.clone())
+                .unwrap_or_else(|| ethers::utils::to_checksum(&address, None)),
+            None => "Contract Creation".into(),
+        }
+    }
+}
+
+/// Formats the amount using the decimals, without the trailing zeros.
+pub fn format_units(amount: U256, decimals: u8) -> String {
+    let amount = ethers::utils::format_units(amount, decimals as u32)
+        .unwrap_or_else(|_| amount.to_string());
+    if amount.contains('.') {
+        amount
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    } else {
+        amount
+    }
+}
+
+/// Formats the UNIX timestamp as a UTC date and time, e.g.
+/// `2023-02-01 13:45:00`.
+pub fn format_timestamp(timestamp: u64) -> String {
+    let days = (timestamp / 86_400) as i64;
+    let seconds = timestamp % 86_400;
+    // Howard Hinnant's `civil_from_days`.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        seconds / 3_600,
+        seconds % 3_600 / 60,
+        seconds % 60
+    )
+}
+
+/// Human readable table lines of the entries.
+pub fn table(entries: &[HistoryEntry], contacts: &[Contact]) -> Vec<String> {
+    let rows = entries
+        .iter()
+        .map(|e| {
+            let status = if e.failed { " (Failed)" } else { "" };
+            [
+                format_timestamp(e.timestamp),
+                e.direction.to_string(),
+                e.counterparty_label(contacts),
+                format!("{} {}{}", e.units(), e.symbol, status),
+                format!("{:?}", e.hash),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let header = ["Date (UTC)", "Dir", "Counterparty", "Amount", "Hash"]
+        .map(String::from);
+    let mut widths = header.clone().map(|h| h.len());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    std::iter::once(&header)
+        .chain(&rows)
+        .map(|row| {
+            row.iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{cell:<width$}"))
+                .collect::<Vec<_>>()
+                .join(" | ")
+                .trim_end()
+                .to_string()
+        })
+        .collect()
+}
+
+/// Writes the entries as CSV, the oldest first, for accounting.
+///
+/// The fee of a transaction is only written once, on its first transfer,
+/// so the fees column can be summed.
+pub fn write_csv(
+    mut writer: impl std::io::Write,
+    entries: &[HistoryEntry],
+    network: &str,
+    currency_symbol: &str,
+    contacts: &[Contact],
+) -> Result<()> {
+    let fee_header = format!("fee ({currency_symbol})");
+    let header = [
+        "timestamp",
+        "hash",
+        "network",
+        "direction",
+        "token",
+        "token address",
+        "amount",
+        &fee_header,
+        "counterparty",
+        "status",
+    ];
+    writeln!(writer, "{}", csv_line(&header))?;
+    let mut entries = entries.iter().collect::<Vec<_>>();
+    entries.sort_by_key(|e| e.timestamp);
+    let mut paid = HashSet::new();
+    for e in entries {
+        let fee = match e.fee {
+            Some(fee) if paid.insert(e.hash) => format_units(fee, 18),
+            _ => String::new(),
+        };
+        let row = [
+            format_timestamp(e.timestamp),
+            format!("{:?}", e.hash),
+            network.to_string(),
+            e.direction.to_string(),
+            e.symbol.clone(),
+            e.token
+                .map(|t| ethers::utils::to_checksum(&t, None))
+                .unwrap_or_default(),
+            e.units(),
+            fee,
+            e.counterparty_label(contacts),
+            if e.failed { "Failed" } else { "Success" }.to_string(),
+        ];
+        writeln!(writer, "{}", csv_line(&row))?;
+    }
+    Ok(())
+}
+
+/// Joins the quoted fields, the fields that a spreadsheet would evaluate as
+/// a formula (e.g. a token symbol starting with `=`) are prefixed with `'`.
+fn csv_line(fields: &[impl AsRef<str>]) -> String {
+    fields
+        .iter()
+        .map(|field| {
+            let field = field.as_ref();
+            let field = if field.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+                format!("'{field}")
+            } else {
+                field.to_string()
+            };
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00");
+        assert_eq!(format_timestamp(1_675_259_100), "2023-02-01 13:45:00");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29 00:00:00");
+    }
+
+    #[test]
+    fn directions_and_labels() {
+        let account = Address::repeat_byte(1);
+        let friend = Address::repeat_byte(2);
+        assert_eq!(
+            Direction::new(account, account, Some(friend)),
+            Direction::Out
+        );
+        assert_eq!(
+            Direction::new(account, friend, Some(account)),
+            Direction::In
+        );
+        assert_eq!(
+            Direction::new(account, account, Some(account)),
+            Direction::SelfTransfer
+        );
+        let entry = HistoryEntry {
+            timestamp: 0,
+            hash: H256::zero(),
+            log_index: None,
+            direction: Direction::Out,
+            counterparty: Some(friend),
+            token: None,
+            symbol: "ETH".into(),
+            amount: ethers::utils::parse_ether("1.5").unwrap(),
+            decimals: 18,
+            fee: None,
+            failed: false,
+        };
+        let contacts = vec![Contact {
+            name: "Friend".into(),
+            address: friend,
+        }];
+        assert_eq!(entry.units(), "1.5");
+        assert_eq!(entry.counterparty_label(&contacts), "Friend");
+        let lines = table(&[entry], &contacts);
+        assert!(
+            lines[0].starts_with("Date (UTC)          | Dir | Counterparty")
+        );
+        assert!(lines[1].contains("| OUT | Friend       | 1.5 ETH |"));
+    }
+
+    #[test]
+    fn csv_export() {
+        let entry = HistoryEntry {
+            timestamp: 1_675_259_100,
+            hash: H256::zero(),
+            log_index: None,
+            direction: Direction::In,
+            counterparty: Some(Address::repeat_byte(2)),
+            token: None,
+            symbol: "ETH".into(),
+            amount: ethers::utils::parse_ether("1.5").unwrap(),
+            decimals: 18,
+            fee: Some(U256::from(21_000_000_000_000u64)),
+            failed: false,
+        };
+        let contacts = vec![Contact {
+            name: "Smith, John".into(),
+            address: Address::repeat_byte(2),
+        }];
+        let mut csv = Vec::new();
+        write_csv(
+            &mut csv,
+            &[entry.clone(), entry],
+            "mainnet",
+            "ETH",
+            &contacts,
+        )
+        .unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines[0],
+            "timestamp,hash,network,direction,token,token address,amount,\
+             fee (ETH),counterparty,status"
+        );
+        let hash = format!("{:?}", H256::zero());
+        assert_eq!(
+            lines[1],
+            format!(
+                "2023-02-01 13:45:00,{hash},mainnet,IN,ETH,,1.5,0.000021,\
+                 \"Smith, John\",Success"
+            )
+        );
+        // the fee is only counted once per transaction.
+        assert!(lines[2].contains(",1.5,,"));
+    }
+
+    #[test]
+    fn csv_formulas() {
+        assert_eq!(
+            csv_line(&["=HYPERLINK(\"x\")", "-1", "@A1", "1.5", "ETH"]),
+            "\"'=HYPERLINK(\"\"x\"\")\",'-1,'@A1,1.5,ETH"
+        );
+        assert_eq!(csv_line(&["+1,5"]), "\"'+1,5\"");
+    }
+}
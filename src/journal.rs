@@ -0,0 +1,280 @@
+//! The opt-in, passphrase encrypted, journal of the sent transactions.
+
+use color_eyre::Result;
+use ethers::types::{Address, H256, U256};
+use serde::{Deserialize, Serialize};
+
+use crate::history::{Direction, HistoryEntry};
+
+/// The local journal file, encrypted.
+const JOURNAL_PATH: &str = "shekozwallet-journal.enc";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransactionStatus {
+    Pending,
+    Confirmed,
+    Failed,
+    /// Replaced by another transaction with the same nonce, or dropped.
+    Dropped,
+}
+
+impl std::fmt::Display for TransactionStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Pending => write!(f, "Pending"),
+            Self::Confirmed => write!(f, "Confirmed"),
+            Self::Failed => write!(f, "Failed"),
+            Self::Dropped => write!(f, "Dropped"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// UNIX timestamp, in seconds, of when the transaction was sent.
+    pub timestamp: u64,
+    pub network: String,
+    pub hash: H256,
+    pub nonce: U256,
+    pub from: Address,
+    /// The account receiving the funds, or the called contract.
+    pub recipient: Option<Address>,
+    /// The sent amounts, empty if the transaction sends nothing.
+    pub transfers: Vec<Transfer>,
+    pub status: TransactionStatus,
+    /// The paid fee, in wei, once the transaction is mined.
+    #[serde(default)]
+    pub fee: Option<U256>,
+}
+
+/// An amount of the native currency or of a token, sent by a transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transfer {
+    /// The token contract, `None` for the native currency.
+    pub token: Option<Address>,
+    /// The token symbol, or the network currency.
+    pub symbol: String,
+    /// The sent amount, in the smallest token unit.
+    pub amount: U256,
+    pub decimals: u8,
+    /// The id of the ERC721 or ERC1155 token.
+    pub token_id: Option<U256>,
+}
+
+impl Transfer {
+    /// The symbol with the token id, if any, e.g. `BAYC #42`.
+    fn label(&self) -> String {
+        match self.token_id {
+            Some(id) => format!("{} #{}", self.symbol, id),
+            None => self.symbol.clone(),
+        }
+    }
+}
+
+impl std::fmt::Display for Transfer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {}",
+            crate::history::format_units(self.amount, self.decimals),
+            self.label()
+        )
+    }
+}
+
+impl JournalEntry {
+    /// Returns `true` if the entry matches the search query, which could be
+    /// a part of the hash, an address, the token or the network.
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return true;
+        }
+        let mut fields = vec![
+            format!("{:?}", self.hash),
+            format!("{:?}", self.from),
+            self.recipient.map(|r| format!("{r:?}")).unwrap_or_default(),
+            self.network.to_lowercase(),
+            self.status.to_string().to_lowercase(),
+        ];
+        for transfer in &self.transfers {
+            fields.push(transfer.symbol.to_lowercase());
+            if let Some(token) = transfer.token {
+                fields.push(format!("{token:?}"));
+            }
+        }
+        fields.iter().any(|field| field.contains(&query))
+    }
+
+    /// The history entries of the transfers, the fee is only set on the
+    /// first one. A transaction sending nothing is kept for its fee, as a
+    /// zero amount of the network `currency`.
+    pub fn history(&self, currency: &str) -> Vec<HistoryEntry> {
+        let nothing = [Transfer {
+            token: None,
+            symbol: currency.to_string(),
+            amount: U256::zero(),
+            decimals: 18,
+            token_id: None,
+        }];
+        let transfers = if self.transfers.is_empty() {
+            &nothing[..]
+        } else {
+            &self.transfers[..]
+        };
+        transfers
+            .iter()
+            .enumerate()
+            .map(|(i, transfer)| HistoryEntry {
+                timestamp: self.timestamp,
+                hash: self.hash,
+                log_index: None,
+                direction: Direction::new(self.from, self.from, self.recipient),
+                counterparty: self.recipient,
+                token: transfer.token,
+                symbol: transfer.label(),
+                amount: transfer.amount,
+                decimals: transfer.decimals,
+                fee: if i == 0 { self.fee } else { None },
+                failed: self.status == TransactionStatus::Failed,
+            })
+            .collect()
+    }
+}
+
+impl std::fmt::Display for JournalEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let recipient = self
+            .recipient
+            .map(|r| ethers::utils::to_checksum(&r, None))
+            .unwrap_or_else(|| "Contract Creation".into());
+        let transfers = if self.transfers.is_empty() {
+            String::from("Call")
+        } else {
+            self.transfers
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        write!(
+            f,
+            "[{}] {} to {} on {} ({:?})",
+            self.status, transfers, recipient, self.network, self.hash
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Journal {
+    pub entries: Vec<JournalEntry>,
+}
+
+impl Journal {
+    /// Load the journal, an empty one is returned if it does not exist yet.
+    pub fn load(passphrase: &str) -> Result<Self> {
+        let journal = crate::encrypted::load(JOURNAL_PATH, passphrase)?;
+        Ok(journal.unwrap_or_default())
+    }
+
+    pub fn save(&self, passphrase: &str) -> Result<()> {
+        crate::encrypted::save(JOURNAL_PATH, passphrase, self)
+    }
+
+    /// Returns `true` if the journal file was already created.
+    pub fn exists() -> bool {
+        std::path::Path::new(JOURNAL_PATH).exists()
+    }
+
+    /// Adds the entry, or replaces the one with the same hash.
+    pub fn upsert(&mut self, entry: JournalEntry) {
+        match self.entries.iter_mut().find(|e| e.hash == entry.hash) {
+            Some(existing) => *existing = entry,
+            None => self.entries.push(entry),
+        }
+    }
+
+    /// The entries matching the query, the most recent first.
+    pub fn search(&self, query: &str) -> Vec<&JournalEntry> {
+        let mut entries = self
+            .entries
+            .iter()
+            .filter(|e| e.matches(query))
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|e| std::cmp::Reverse(e.timestamp));
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(timestamp: u64, token: &str) -> JournalEntry {
+        JournalEntry {
+            timestamp,
+            network: "mainnet".into(),
+            hash: H256::from_low_u64_be(timestamp),
+            nonce: U256::from(timestamp),
+            from: Address::repeat_byte(1),
+            recipient: Some(Address::repeat_byte(2)),
+            transfers: vec![Transfer {
+                token: None,
+                symbol: token.into(),
+                amount: U256::from(1_500_000),
+                decimals: 6,
+                token_id: None,
+            }],
+            status: TransactionStatus::Pending,
+            fee: None,
+        }
+    }
+
+    #[test]
+    fn search_entries() {
+        let mut journal = Journal::default();
+        journal.upsert(entry(1, "ETH"));
+        journal.upsert(entry(2, "USDC"));
+        let mut confirmed = entry(1, "ETH");
+        confirmed.status = TransactionStatus::Confirmed;
+        journal.upsert(confirmed);
+        assert_eq!(journal.entries.len(), 2);
+        let all = journal.search("");
+        assert_eq!(all[0].timestamp, 2);
+        assert_eq!(journal.search("usdc").len(), 1);
+        assert_eq!(journal.search("confirmed")[0].transfers[0].symbol, "ETH");
+        assert_eq!(journal.search("0x0202").len(), 2);
+        assert!(journal.search("bsc").is_empty());
+    }
+
+    #[test]
+    fn history_entries() {
+        let mut entry = entry(1, "USDC");
+        entry.fee = Some(U256::from(21_000));
+        let history = entry.history("ETH");
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].direction, Direction::Out);
+        assert_eq!(history[0].units(), "1.5");
+        assert_eq!(history[0].decimals, 6);
+        assert_eq!(history[0].symbol, "USDC");
+        entry.transfers.push(Transfer {
+            token: Some(Address::repeat_byte(3)),
+            symbol: "BAYC".into(),
+            amount: U256::one(),
+            decimals: 0,
+            token_id: Some(U256::from(42)),
+        });
+        assert!(entry.to_string().contains("1.5 USDC, 1 BAYC #42 to"));
+        let history = entry.history("ETH");
+        assert_eq!(history[1].symbol, "BAYC #42");
+        assert_eq!(history[1].token, Some(Address::repeat_byte(3)));
+        // the fee is only paid once.
+        assert_eq!(history[0].fee, Some(U256::from(21_000)));
+        assert_eq!(history[1].fee, None);
+        entry.transfers.clear();
+        let history = entry.history("ETH");
+        assert_eq!(history[0].symbol, "ETH");
+        assert!(history[0].amount.is_zero());
+        assert_eq!(history[0].fee, Some(U256::from(21_000)));
+    }
+}
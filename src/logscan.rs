@@ -0,0 +1,414 @@
+//! The account history, reconstructed from the node only, without any
+//! explorer.
+//!
+//! ERC20 transfers are found using the `Transfer` logs where the account is
+//! the indexed sender or recipient, the native transfers by scanning the
+//! transactions of the recent blocks, transfers done by contracts (internal
+//! transactions) are not found.
+//!
+//! The results are cached, encrypted, with the first and last scanned
+//! blocks so the next scans only fetch the new blocks, or the older ones
+//! when asked to.
+
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+
+use color_eyre::eyre;
+use color_eyre::Result;
+use ethers::contract::EthEvent;
+use ethers::providers::Middleware;
+use ethers::types::{Address, Filter, Log, H256, U256, U64};
+use serde::{Deserialize, Serialize};
+
+use crate::decoder::TokenInfo;
+use crate::erc20::TransferFilter;
+use crate::history::{Direction, HistoryEntry};
+use crate::state::EthersClient;
+
+/// The local history cache file, encrypted.
+const CACHE_PATH: &str = "shekozwallet-history.enc";
+
+/// Number of recent blocks that are not scanned, as they could still be
+/// reorganized and the transfers removed from the chain.
+pub const REORG_SAFETY_BLOCKS: u64 = 12;
+
+/// Number of blocks per `eth_getLogs` request, most nodes limit the range.
+pub const LOG_CHUNK_BLOCKS: u64 = 2_000;
+
+/// Number of blocks scanned for `Transfer` logs on the first scan.
+pub const ERC20_SCAN_BLOCKS: u64 = 200_000;
+
+/// Number of recent blocks scanned for native transfers, every block has to
+/// be fetched with its transactions.
+pub const NATIVE_SCAN_BLOCKS: u64 = 1_000;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryCache {
+    accounts: Vec<AccountHistory>,
+}
+
+/// The scanned history of an account on a network.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountHistory {
+    pub chain_id: U256,
+    pub account: Address,
+    /// The first block scanned for `Transfer` logs.
+    #[serde(default)]
+    pub erc20_start: Option<u64>,
+    /// The last block scanned for `Transfer` logs.
+    pub erc20_checkpoint: Option<u64>,
+    /// The first block scanned for native transfers, since the last gap.
+    #[serde(default)]
+    pub native_start: Option<u64>,
+    /// The last block scanned for native transfers.
+    pub native_checkpoint: Option<u64>,
+    pub entries: Vec<HistoryEntry>,
+}
+
+impl HistoryCache {
+    /// Load the cache, an empty one is returned if it does not exist yet.
+    pub fn load(passphrase: &str) -> Result<Self> {
+        let cache = crate::encrypted::load(CACHE_PATH, passphrase)?;
+        Ok(cache.unwrap_or_default())
+    }
+
+    pub fn save(&self, passphrase: &str) -> Result<()> {
+        crate::encrypted::save(CACHE_PATH, passphrase, self)
+    }
+
+    /// Returns `true` if the cache file was already created.
+    pub fn exists() -> bool {
+        std::path::Path::new(CACHE_PATH).exists()
+    }
+
+    /// The history of the account, if it was already scanned.
+    pub fn get(
+        &self,
+        chain_id: U256,
+        account: Address,
+    ) -> Option<&AccountHistory> {
+        self.accounts
+            .iter()
+            .find(|h| h.chain_id == chain_id && h.account == account)
+    }
+
+    /// The history of the account, created empty if never scanned.
+    pub fn account(
+        &mut self,
+        chain_id: U256,
+        account: Address,
+    ) -> &mut AccountHistory {
+        let i = match self
+            .accounts
+            .iter()
+            .position(|h| h.chain_id == chain_id && h.account == account)
+        {
+            Some(i) => i,
+            None => {
+                self.accounts.push(AccountHistory {
+                    chain_id,
+                    account,
+                    erc20_start: None,
+                    erc20_checkpoint: None,
+                    native_start: None,
+                    native_checkpoint: None,
+                    entries: Vec::new(),
+                });
+                self.accounts.len() - 1
+            }
+        };
+        &mut self.accounts[i]
+    }
+}
+
+impl AccountHistory {
+    /// Adds the entries that are not already in the history, a transfer is
+    /// identified by its transaction hash and log index.
+    pub fn merge(&mut self, entries: Vec<HistoryEntry>) {
+        let mut known = self
+            .entries
+            .iter()
+            .map(|e| (e.hash, e.log_index))
+            .collect::<HashSet<_>>();
+        for entry in entries {
+            if known.insert((entry.hash, entry.log_index)) {
+                self.entries.push(entry);
+            }
+        }
+        self.entries.sort_by_key(|e| std::cmp::Reverse(e.timestamp));
+    }
+
+    /// The scanned blocks, the transfers before them are not in the
+    /// history.
+    pub fn scanned_blocks(&self, currency_symbol: &str) -> Vec<String> {
+        let blocks = |start: Option<u64>, end: Option<u64>| match (start, end) {
+            (Some(start), Some(end)) => format!("blocks {start} to {end}"),
+            _ => String::from("no blocks"),
+        };
+        vec![
+            format!(
+                "Token transfers scanned in {}",
+                blocks(self.erc20_start, self.erc20_checkpoint)
+            ),
+            format!(
+                "{} transfers scanned in {}",
+                currency_symbol,
+                blocks(self.native_start, self.native_checkpoint)
+            ),
+        ]
+    }
+}
+
+/// Splits the blocks range, inclusive, into `eth_getLogs` sized chunks.
+pub fn chunks(from: u64, to: u64) -> Vec<(u64, u64)> {
+    (from..=to)
+        .step_by(LOG_CHUNK_BLOCKS as usize)
+        .map(|start| (start, to.min(start + LOG_CHUNK_BLOCKS - 1)))
+        .collect()
+}
+
+/// Fetches the ERC20 transfers from or to the account in the blocks range,
+/// inclusive.
+///
+/// The fetched tokens info are kept in `tokens`, to avoid fetching them
+/// again for the next ranges.
+pub async fn scan_erc20(
+    client: &EthersClient,
+    account: Address,
+    (from_block, to_block): (u64, u64),
+    tokens: &mut HashMap<Address, Option<TokenInfo>>,
+) -> Result<Vec<HistoryEntry>> {
+    let filter = Filter::new()
+        .from_block(from_block)
+        .to_block(to_block)
+        .topic0(TransferFilter::signature());
+    let account_topic = H256::from(account);
+    let mut logs = client
+        .get_logs(&filter.clone().topic1(account_topic))
+        .await
+        .map_err(|e| eyre::eyre!("Failed to fetch transfer logs: {}", e))?;
+    let incoming = client
+        .get_logs(&filter.topic2(account_topic))
+        .await
+        .map_err(|e| eyre::eyre!("Failed to fetch transfer logs: {}", e))?;
+    // self transfers are returned by both queries.
+    for log in incoming {
+        if !logs.contains(&log) {
+            logs.push(log);
+        }
+    }
+    let mut timestamps = HashMap::new();
+    let mut entries = Vec::new();
+    // ERC721 transfers have the same signature, but with 4 topics.
+    let logs = logs
+        .into_iter()
+        .filter(|log| log.topics.len() == 3 && log.data.len() == 32);
+    for log in logs {
+        let token = log.address;
+        if let Entry::Vacant(entry) = tokens.entry(token) {
+            entry.insert(TokenInfo::fetch(client, token).await);
+        }
+        let info = match tokens[&token].clone() {
+            Some(info) => info,
+            None => continue,
+        };
+        let (hash, block) = match (log.transaction_hash, log.block_number) {
+            (Some(hash), Some(block)) => (hash, block),
+            _ => continue,
+        };
+        let timestamp = match timestamps.get(&block) {
+            Some(timestamp) => *timestamp,
+            None => {
+                let timestamp = block_timestamp(client, block).await?;
+                timestamps.insert(block, timestamp);
+                timestamp
+            }
+        };
+        let mut entry = transfer_entry(account, &log, info, timestamp, hash);
+        if entry.direction != Direction::In {
+            let (fee, failed) = receipt_fee(client, account, hash).await?;
+            entry.fee = fee;
+            entry.failed = failed;
+        }
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+fn transfer_entry(
+    account: Address,
+    log: &Log,
+    info: TokenInfo,
+    timestamp: u64,
+    hash: H256,
+) -> HistoryEntry {
+    let from = Address::from(log.topics[1]);
+    let to = Address::from(log.topics[2]);
+    let direction = Direction::new(account, from, Some(to));
+    let counterparty = match direction {
+        Direction::In => from,
+        Direction::Out | Direction::SelfTransfer => to,
+    };
+    HistoryEntry {
+        timestamp,
+        hash,
+        log_index: log.log_index.map(|i| i.as_u64()),
+        direction,
+        counterparty: Some(counterparty),
+        token: Some(log.address),
+        symbol: info.symbol,
+        amount: U256::from_big_endian(&log.data),
+        decimals: info.decimals,
+        fee: None,
+        failed: false,
+    }
+}
+
+/// Fetches the native transfers from or to the account in the block.
+pub async fn scan_native(
+    client: &EthersClient,
+    account: Address,
+    block: u64,
+    currency_symbol: &str,
+) -> Result<Vec<HistoryEntry>> {
+    let block = client
+        .get_block_with_txs(block)
+        .await
+        .map_err(|e| eyre::eyre!("Failed to fetch block {}: {}", block, e))?
+        .ok_or_else(|| eyre::eyre!("Block {} not found", block))?;
+    let timestamp = block.timestamp.as_u64();
+    let mut entries = Vec::new();
+    for tx in block.transactions {
+        let sent = tx.from == account;
+        let received = tx.to == Some(account) && !tx.value.is_zero();
+        if !sent && !received {
+            continue;
+        }
+        let direction = Direction::new(account, tx.from, tx.to);
+        let counterparty = match direction {
+            Direction::In => Some(tx.from),
+            Direction::Out | Direction::SelfTransfer => tx.to,
+        };
+        let (fee, failed) = receipt_fee(client, account, tx.hash).await?;
+        entries.push(HistoryEntry {
+            timestamp,
+            hash: tx.hash,
+            log_index: None,
+            direction,
+            counterparty,
+            token: None,
+            symbol: currency_symbol.to_string(),
+            amount: tx.value,
+            decimals: 18,
+            fee,
+            failed,
+        });
+    }
+    Ok(entries)
+}
+
+async fn block_timestamp(client: &EthersClient, block: U64) -> Result<u64> {
+    let block = client
+        .get_block(block)
+        .await
+        .map_err(|e| eyre::eyre!("Failed to fetch block {}: {}", block, e))?
+        .ok_or_else(|| eyre::eyre!("Block {} not found", block))?;
+    Ok(block.timestamp.as_u64())
+}
+
+/// The fee paid by the account, if it sent the transaction, and whether
+/// the transaction reverted.
+async fn receipt_fee(
+    client: &EthersClient,
+    account: Address,
+    hash: H256,
+) -> Result<(Option<U256>, bool)> {
+    let receipt = client.get_transaction_receipt(hash).await.map_err(|e| {
+        eyre::eyre!("Failed to fetch receipt {:?}: {}", hash, e)
+    })?;
+    let receipt = match receipt {
+        Some(receipt) => receipt,
+        None => return Ok((None, false)),
+    };
+    let failed = receipt.status == Some(U64::zero());
+    let fee = if receipt.from == account {
+        receipt
+            .gas_used
+            .zip(receipt.effective_gas_price)
+            .map(|(used, price)| used.saturating_mul(price))
+    } else {
+        None
+    };
+    Ok((fee, failed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_chunks() {
+        assert_eq!(chunks(0, 0), vec![(0, 0)]);
+        assert_eq!(
+            chunks(100, 4_500),
+            vec![(100, 2_099), (2_100, 4_099), (4_100, 4_500)]
+        );
+        assert!(chunks(10, 9).is_empty());
+    }
+
+    #[test]
+    fn transfer_logs() {
+        let account = Address::repeat_byte(1);
+        let friend = Address::repeat_byte(2);
+        let log = Log {
+            address: Address::repeat_byte(5),
+            topics: vec![
+                TransferFilter::signature(),
+                H256::from(friend),
+                H256::from(account),
+            ],
+            data: ethers::abi::encode(&[ethers::abi::Token::Uint(U256::from(
+                1_500_000,
+            ))])
+            .into(),
+            log_index: Some(3.into()),
+            ..Default::default()
+        };
+        let info = TokenInfo {
+            symbol: "USDC".into(),
+            decimals: 6,
+        };
+        let entry = transfer_entry(account, &log, info, 1, H256::zero());
+        assert_eq!(entry.log_index, Some(3));
+        assert_eq!(entry.direction, Direction::In);
+        assert_eq!(entry.counterparty, Some(friend));
+        assert_eq!(entry.units(), "1.5");
+        let mut history =
+            HistoryCache::default().account(1.into(), account).clone();
+        history.merge(vec![entry.clone(), entry.clone()]);
+        assert_eq!(history.entries.len(), 1);
+        // another transfer of the same transaction.
+        let second = HistoryEntry {
+            log_index: Some(4),
+            ..entry.clone()
+        };
+        history.merge(vec![entry, second]);
+        assert_eq!(history.entries.len(), 2);
+    }
+
+    #[test]
+    fn scanned_blocks() {
+        let mut history = HistoryCache::default()
+            .account(1.into(), Address::zero())
+            .clone();
+        history.erc20_start = Some(100);
+        history.erc20_checkpoint = Some(4_500);
+        assert_eq!(
+            history.scanned_blocks("ETH"),
+            vec![
+                "Token transfers scanned in blocks 100 to 4500",
+                "ETH transfers scanned in no blocks",
+            ]
+        );
+    }
+}
@@ -2,10 +2,30 @@
 
 use inquire::ui::{Attributes, Color, RenderConfig, StyleSheet, Styled};
 
+mod abi;
+mod chainlink;
 mod config;
+mod confirmation;
+mod decoder;
+mod eip712;
+mod encrypted;
+mod ens;
+mod erc1155;
 mod erc20;
+mod erc721;
+mod explorer;
+mod history;
+mod journal;
+mod logscan;
+mod message;
+mod policy;
+mod price;
 mod qrscanner;
+mod receipt;
+mod safety;
+mod simulation;
 mod state;
+mod weth;
 
 #[tokio::main]
 async fn main() -> color_eyre::Result<()> {
@@ -0,0 +1,71 @@
+//! EIP-191 personal message helpers.
+
+use ethers::types::{Address, Signature, H256};
+use serde::{Deserialize, Serialize};
+
+/// Signed message envelope, in the same format used by MyCrypto and
+/// Etherscan's "Verify Signature" tool.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedMessage {
+    pub address: Address,
+    pub msg: String,
+    pub sig: String,
+    pub version: String,
+}
+
+impl SignedMessage {
+    pub fn new(
+        address: Address,
+        message: &[u8],
+        signature: &Signature,
+    ) -> Self {
+        Self {
+            address,
+            msg: display(message),
+            sig: format!("0x{signature}"),
+            version: String::from("2"),
+        }
+    }
+}
+
+/// The EIP-191 hash of the message, this what the signer actually signs.
+pub fn hash(message: &[u8]) -> H256 {
+    ethers::utils::hash_message(message)
+}
+
+/// The message as text if it is valid UTF-8, otherwise as `0x` prefixed hex.
+pub fn display(message: &[u8]) -> String {
+    match std::str::from_utf8(message) {
+        Ok(text) => text.to_string(),
+        Err(_) => format!("0x{}", hex::encode(message)),
+    }
+}
+
+/// Parse a `0x` prefixed hex message into its bytes.
+pub fn parse_hex(s: &str) -> Result<Vec<u8>, hex::FromHexError> {
+    let s = s.trim();
+    hex::decode(s.strip_prefix("0x").unwrap_or(s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_text_or_hex() {
+        assert_eq!(display(b"Hello\nWorld"), "Hello\nWorld");
+        assert_eq!(display(&[0xff, 0x00]), "0xff00");
+        assert_eq!(parse_hex("0xff00").unwrap(), vec![0xff, 0x00]);
+    }
+
+    #[test]
+    fn eip191_hash() {
+        // https://eips.ethereum.org/EIPS/eip-191
+        assert_eq!(
+            hash(b"Hello World"),
+            "0xa1de988600a42c4b4ab089b619297c17d53cffae5d5120d82d8a92d0bb3b78f2"
+                .parse()
+                .unwrap()
+        );
+    }
+}
@@ -0,0 +1,753 @@
+//! Spending policies, checked after the transaction review and before
+//! signing it.
+
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use color_eyre::eyre;
+use color_eyre::Result;
+use ethers::abi::{AbiDecode, Token};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, U256};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Contact, Network, NetworkPolicy, SpendingLimit};
+use crate::decoder::Interface;
+use crate::erc1155::Erc1155Calls;
+use crate::erc20::Erc20Calls;
+use crate::erc721::Erc721Calls;
+use crate::state::EthersClient;
+use crate::weth::WethCalls;
+
+/// The local file that tracks the spent amounts, for the daily limits,
+/// encrypted.
+const LEDGER_PATH: &str = "shekozwallet-spending.enc";
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// An amount of the native currency (`token` is `None`) or of an ERC20
+/// token, spent by a transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spend {
+    pub token: Option<Address>,
+    pub amount: U256,
+    pub symbol: String,
+    pub decimals: u8,
+}
+
+impl Spend {
+    fn display_amount(&self) -> String {
+        format!(
+            "{} {}",
+            crate::history::format_units(self.amount, self.decimals),
+            self.symbol
+        )
+    }
+
+    fn parse_limit(&self, limit: &str) -> Result<U256> {
+        let limit = ethers::utils::parse_units(limit, self.decimals as u32)
+            .map_err(|e| {
+                eyre::eyre!(
+                    "Invalid {} policy limit {}: {}",
+                    self.symbol,
+                    limit,
+                    e
+                )
+            })?;
+        Ok(limit.into())
+    }
+}
+
+/// An allowance of an ERC20 token granted, or lowered, by a transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Approval {
+    pub spender: Address,
+    pub token: Address,
+    pub amount: U256,
+    /// `true` if the amount is subtracted from the allowance, e.g. with
+    /// `decreaseAllowance`.
+    pub decrease: bool,
+}
+
+impl Approval {
+    /// Returns `true` if the approval only revokes or lowers an allowance.
+    fn is_revocation(&self) -> bool {
+        self.decrease || self.amount.is_zero() || self.spender.is_zero()
+    }
+}
+
+/// What a transaction spends, as decoded from the transaction itself.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Spending {
+    /// The account receiving the funds, or the called contract, `None` when
+    /// wrapping or unwrapping the native currency, or approving a spender.
+    pub recipient: Option<Address>,
+    pub spends: Vec<Spend>,
+    /// The allowance granted by the transaction, it is not spent yet.
+    pub approval: Option<Approval>,
+    /// The transferred token and amount, whose decimals could not be
+    /// fetched, hence the amount could not be checked.
+    pub unknown_token: Option<(Address, U256)>,
+    /// The ERC721 and ERC1155 tokens sent, they are not limited.
+    pub nfts: Vec<NftTransfer>,
+}
+
+/// An ERC721 or ERC1155 token sent by a transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NftTransfer {
+    pub contract: Address,
+    pub token_id: U256,
+    /// Always 1 for the ERC721 tokens.
+    pub amount: U256,
+}
+
+/// The recipient and the amounts of a transaction, before fetching the
+/// tokens info.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Decoded {
+    recipient: Option<Address>,
+    value: Option<U256>,
+    /// The token and the amount transferred.
+    token_amount: Option<(Address, U256)>,
+    approval: Option<Approval>,
+    nfts: Vec<NftTransfer>,
+}
+
+impl Spending {
+    pub async fn from_tx(
+        client: &EthersClient,
+        network: &Network,
+        tx: &TypedTransaction,
+    ) -> Self {
+        let decoded = decode(network, tx);
+        let mut spending = Self {
+            recipient: decoded.recipient,
+            spends: Vec::new(),
+            approval: decoded.approval,
+            unknown_token: None,
+            nfts: decoded.nfts,
+        };
+        if let Some(value) = decoded.value {
+            spending.spends.push(Spend {
+                token: None,
+                amount: value,
+                symbol: network.currency_symbol.clone(),
+                decimals: 18,
+            });
+        }
+        if let Some((token, amount)) = decoded.token_amount {
+            // without decimals it is an ERC721 `transferFrom`, or a failed
+            // request, `check` rejects it if the token has limits.
+            match crate::decoder::TokenInfo::fetch(client, token).await {
+                Some(info) => spending.spends.push(Spend {
+                    token: Some(token),
+                    amount,
+                    symbol: info.symbol,
+                    decimals: info.decimals,
+                }),
+                None => spending.unknown_token = Some((token, amount)),
+            }
+        }
+        spending
+    }
+}
+
+fn decode(network: &Network, tx: &TypedTransaction) -> Decoded {
+    let from = tx.from().copied().unwrap_or_default();
+    let to = tx.to_addr().copied();
+    let calldata = tx.data().map(|d| d.to_vec()).unwrap_or_default();
+    let mut decoded = Decoded {
+        recipient: to,
+        value: tx.value().copied().filter(|v| !v.is_zero()),
+        token_amount: None,
+        approval: None,
+        nfts: Vec::new(),
+    };
+    let to = match to {
+        Some(to) if !calldata.is_empty() => to,
+        _ => return decoded,
+    };
+    // the wrapped funds stay in the account.
+    if Some(to) == network.wrapped_native_token
+        && matches!(
+            WethCalls::decode(&calldata),
+            Ok(WethCalls::Deposit(_) | WethCalls::Withdraw(_))
+        )
+    {
+        decoded.recipient = None;
+        return decoded;
+    }
+    let transfer = match Erc20Calls::decode(&calldata) {
+        Ok(Erc20Calls::Transfer(call)) => Some((call.recipient, call.amount)),
+        Ok(Erc20Calls::TransferFrom(call)) if call.sender == from => {
+            Some((call.recipient, call.amount))
+        }
+        _ => None,
+    };
+    if let Some((recipient, amount)) = transfer {
+        decoded.recipient = Some(recipient);
+        decoded.token_amount = Some((to, amount));
+        return decoded;
+    }
+    if let Some(approval) = approval(from, to, &calldata) {
+        decoded.recipient = None;
+        decoded.approval = Some(approval);
+        return decoded;
+    }
+    let nft = |token_id, amount| NftTransfer {
+        contract: to,
+        token_id,
+        amount,
+    };
+    if let Ok(Erc721Calls::SafeTransferFrom(call)) =
+        Erc721Calls::decode(&calldata)
+    {
+        decoded.recipient = Some(call.to);
+        decoded.nfts.push(nft(call.token_id, U256::one()));
+    }
+    match Erc1155Calls::decode(&calldata) {
+        Ok(Erc1155Calls::SafeTransferFrom(call)) => {
+            decoded.recipient = Some(call.to);
+            decoded.nfts.push(nft(call.id, call.amount));
+        }
+        Ok(Erc1155Calls::SafeBatchTransferFrom(call)) => {
+            decoded.recipient = Some(call.to);
+            decoded.nfts.extend(
+                call.ids
+                    .into_iter()
+                    .zip(call.amounts)
+                    .map(|(id, amount)| nft(id, amount)),
+            );
+        }
+        _ => {}
+    }
+    decoded
+}
+
+/// The allowance granted, or lowered, by an ERC20 `approve`, the EIP-2612
+/// `increaseAllowance`, `decreaseAllowance` and `permit`, or the Permit2
+/// `approve`.
+fn approval(from: Address, to: Address, calldata: &[u8]) -> Option<Approval> {
+    let allowance = |spender: &Address, token, amount: &U256, decrease| {
+        Some(Approval {
+            spender: *spender,
+            token,
+            amount: *amount,
+            decrease,
+        })
+    };
+    let call = crate::decoder::decode(calldata, None)?;
+    let eip2612 = call.is_defined_by(Interface::Eip2612);
+    match (call.function.name.as_str(), call.args.as_slice()) {
+        ("approve", [Token::Address(spender), Token::Uint(amount)])
+            if call.is_defined_by(Interface::Erc20) =>
+        {
+            allowance(spender, to, amount, false)
+        }
+        (
+            "increaseAllowance",
+            [Token::Address(spender), Token::Uint(amount)],
+        ) if eip2612 => allowance(spender, to, amount, false),
+        (
+            "decreaseAllowance",
+            [Token::Address(spender), Token::Uint(amount)],
+        ) if eip2612 => allowance(spender, to, amount, true),
+        (
+            "permit",
+            [Token::Address(owner), Token::Address(spender), Token::Uint(amount), ..],
+        ) if eip2612 && *owner == from => allowance(spender, to, amount, false),
+        (
+            "approve",
+            [Token::Address(token), Token::Address(spender), Token::Uint(amount), Token::Uint(_)],
+        ) if call.is_defined_by(Interface::Permit2) => {
+            allowance(spender, *token, amount, false)
+        }
+        _ => None,
+    }
+}
+
+/// Checks the spending against the network policy, returns an error if it
+/// is not allowed, otherwise the phrases the user has to type to confirm
+/// the amounts above the confirmation threshold.
+///
+/// The approved spenders have to be allowed recipients, but the allowances
+/// are not counted against the limits, and revoking one is always allowed.
+pub fn check(
+    policy: &NetworkPolicy,
+    network_name: &str,
+    contacts: &[Contact],
+    spending: &Spending,
+    ledger: &Ledger,
+    now: u64,
+) -> Result<Vec<String>> {
+    let spender = spending
+        .approval
+        .as_ref()
+        .filter(|approval| !approval.is_revocation())
+        .map(|approval| approval.spender);
+    for recipient in spending.recipient.into_iter().chain(spender) {
+        let allowed = policy.allowed_recipients.is_empty()
+            || contacts.iter().any(|c| {
+                c.address == recipient
+                    && policy.allowed_recipients.contains(&c.name)
+            });
+        if !allowed {
+            eyre::bail!(
+                "Policy violation: {} is not an allowed recipient on {}",
+                ethers::utils::to_checksum(&recipient, None),
+                network_name
+            );
+        }
+    }
+    if let Some((token, _)) = spending.unknown_token {
+        if policy.tokens.contains_key(&token) {
+            eyre::bail!(
+                "Failed to fetch the decimals of {}, its policy limits can't \
+                 be checked",
+                ethers::utils::to_checksum(&token, None)
+            );
+        }
+    }
+    let mut phrases = Vec::new();
+    for spend in &spending.spends {
+        let limit = match spend.token {
+            Some(token) => policy.tokens.get(&token),
+            None => policy.native.as_ref(),
+        };
+        let SpendingLimit {
+            max_per_transaction,
+            max_daily,
+            confirmation_threshold,
+        } = match limit {
+            Some(limit) => limit,
+            None => continue,
+        };
+        if let Some(max) = max_per_transaction {
+            if spend.amount > spend.parse_limit(max)? {
+                eyre::bail!(
+                    "Policy violation: {} is above the limit of {} {} per \
+                     transaction",
+                    spend.display_amount(),
+                    max,
+                    spend.symbol
+                );
+            }
+        }
+        if let Some(max) = max_daily {
+            let spent = ledger.spent(
+                network_name,
+                spend.token,
+                now.saturating_sub(DAY),
+            );
+            if spent.saturating_add(spend.amount) > spend.parse_limit(max)? {
+                let spent = Spend {
+                    amount: spent,
+                    ..spend.clone()
+                };
+                eyre::bail!(
+                    "Policy violation: {} would exceed the daily limit of {} \
+                     {}, already spent {} in the last 24 hours",
+                    spend.display_amount(),
+                    max,
+                    spend.symbol,
+                    spent.display_amount()
+                );
+            }
+        }
+        if let Some(threshold) = confirmation_threshold {
+            if spend.amount > spend.parse_limit(threshold)? {
+                phrases.push(spend.display_amount());
+            }
+        }
+    }
+    Ok(phrases)
+}
+
+/// Returns `true` if the policy has daily limits, which require the ledger
+/// of the spent amounts.
+pub fn has_daily_limit(policy: &NetworkPolicy) -> bool {
+    policy
+        .native
+        .iter()
+        .chain(policy.tokens.values())
+        .any(|limit| limit.max_daily.is_some())
+}
+
+/// The current UNIX timestamp, in seconds.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// The locally tracked spent amounts of the last 24 hours.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ledger {
+    entries: Vec<LedgerEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct LedgerEntry {
+    timestamp: u64,
+    network: String,
+    token: Option<Address>,
+    amount: U256,
+}
+
+impl Ledger {
+    /// Load the ledger, an empty one is returned if it does not exist yet.
+    pub fn load(passphrase: &str) -> Result<Self> {
+        let ledger = crate::encrypted::load(LEDGER_PATH, passphrase)?;
+        Ok(ledger.unwrap_or_default())
+    }
+
+    pub fn save(&self, passphrase: &str) -> Result<()> {
+        crate::encrypted::save(LEDGER_PATH, passphrase, self)
+    }
+
+    /// Returns `true` if the ledger file was already created.
+    pub fn exists() -> bool {
+        Path::new(LEDGER_PATH).exists()
+    }
+
+    fn spent(&self, network: &str, token: Option<Address>, since: u64) -> U256 {
+        self.entries
+            .iter()
+            .filter(|e| e.timestamp > since)
+            .filter(|e| e.network == network && e.token == token)
+            .fold(U256::zero(), |total, e| total.saturating_add(e.amount))
+    }
+
+    /// Records the spent amounts, dropping the entries older than a day.
+    pub fn record(&mut self, network: &str, spending: &Spending, now: u64) {
+        self.entries
+            .retain(|e| e.timestamp > now.saturating_sub(DAY));
+        for spend in &spending.spends {
+            self.entries.push(LedgerEntry {
+                timestamp: now,
+                network: network.to_string(),
+                token: spend.token,
+                amount: spend.amount,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eth(amount: &str) -> Spend {
+        Spend {
+            token: None,
+            amount: ethers::utils::parse_ether(amount).unwrap(),
+            symbol: "ETH".into(),
+            decimals: 18,
+        }
+    }
+
+    fn policy() -> NetworkPolicy {
+        NetworkPolicy {
+            native: Some(SpendingLimit {
+                max_per_transaction: Some("2".into()),
+                max_daily: Some("2.5".into()),
+                confirmation_threshold: Some("1".into()),
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn per_transaction_limit() {
+        let spending = Spending {
+            recipient: None,
+            spends: vec![eth("2.5")],
+            approval: None,
+            unknown_token: None,
+            nfts: Vec::new(),
+        };
+        let ledger = Ledger::default();
+        let result = check(&policy(), "mainnet", &[], &spending, &ledger, DAY);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn daily_limit_and_phrase() {
+        let spending = Spending {
+            recipient: None,
+            spends: vec![eth("1.5")],
+            approval: None,
+            unknown_token: None,
+            nfts: Vec::new(),
+        };
+        let mut ledger = Ledger::default();
+        let phrases =
+            check(&policy(), "mainnet", &[], &spending, &ledger, DAY).unwrap();
+        assert_eq!(phrases, vec!["1.5 ETH".to_string()]);
+        ledger.record("mainnet", &spending, DAY);
+        // other networks are tracked separately.
+        assert!(check(&policy(), "bsc", &[], &spending, &ledger, DAY).is_ok());
+        let result = check(&policy(), "mainnet", &[], &spending, &ledger, DAY);
+        assert!(result.is_err());
+        // a day later the limit is available again.
+        let later = DAY * 2 + 1;
+        assert!(
+            check(&policy(), "mainnet", &[], &spending, &ledger, later).is_ok()
+        );
+    }
+
+    #[test]
+    fn allowed_recipients() {
+        let friend = Address::repeat_byte(1);
+        let contacts = vec![Contact {
+            name: "Friend".into(),
+            address: friend,
+        }];
+        let policy = NetworkPolicy {
+            allowed_recipients: vec!["Friend".into()],
+            ..Default::default()
+        };
+        let ledger = Ledger::default();
+        let mut spending = Spending {
+            recipient: Some(friend),
+            spends: vec![eth("1")],
+            approval: None,
+            unknown_token: None,
+            nfts: Vec::new(),
+        };
+        assert!(
+            check(&policy, "mainnet", &contacts, &spending, &ledger, 0).is_ok()
+        );
+        spending.recipient = Some(Address::repeat_byte(2));
+        assert!(check(&policy, "mainnet", &contacts, &spending, &ledger, 0)
+            .is_err());
+    }
+
+    fn mainnet() -> Network {
+        crate::config::Config::default()
+            .networks
+            .remove("mainnet")
+            .unwrap()
+    }
+
+    fn tx(to: Address, calldata: Vec<u8>) -> TypedTransaction {
+        ethers::types::Eip1559TransactionRequest::new()
+            .from(Address::repeat_byte(1))
+            .to(to)
+            .data(calldata)
+            .into()
+    }
+
+    fn calldata(signature: &str, args: &[Token]) -> Vec<u8> {
+        ethers::abi::HumanReadableParser::parse_function(signature)
+            .unwrap()
+            .encode_input(args)
+            .unwrap()
+    }
+
+    #[test]
+    fn approvals() {
+        let token = Address::repeat_byte(5);
+        let spender = Address::repeat_byte(2);
+        let amount = U256::from(1_000);
+        let approve = crate::erc20::ApproveCall { spender, amount };
+        let mut expected = Decoded {
+            approval: Some(Approval {
+                spender,
+                token,
+                amount,
+                decrease: false,
+            }),
+            ..Default::default()
+        };
+        let tx = tx(token, ethers::abi::AbiEncode::encode(approve));
+        assert_eq!(decode(&mainnet(), &tx), expected);
+        let increase = calldata(
+            "function increaseAllowance(address spender, uint256 addedValue)",
+            &[Token::Address(spender), Token::Uint(amount)],
+        );
+        assert_eq!(decode(&mainnet(), &self::tx(token, increase)), expected);
+        let permit2 = Address::repeat_byte(6);
+        let approve = calldata(
+            "function approve(address token, address spender, uint160 \
+             amount, uint48 expiration)",
+            &[
+                Token::Address(token),
+                Token::Address(spender),
+                Token::Uint(amount),
+                Token::Uint(U256::from(1_700_000_000)),
+            ],
+        );
+        assert_eq!(decode(&mainnet(), &self::tx(permit2, approve)), expected);
+        let decrease = calldata(
+            "function decreaseAllowance(address spender, uint256 \
+             subtractedValue)",
+            &[Token::Address(spender), Token::Uint(amount)],
+        );
+        expected.approval.as_mut().unwrap().decrease = true;
+        assert_eq!(decode(&mainnet(), &self::tx(token, decrease)), expected);
+    }
+
+    #[test]
+    fn revocations() {
+        let friend = Address::repeat_byte(1);
+        let contacts = vec![Contact {
+            name: "Friend".into(),
+            address: friend,
+        }];
+        let policy = NetworkPolicy {
+            allowed_recipients: vec!["Friend".into()],
+            ..policy()
+        };
+        let token = Address::repeat_byte(5);
+        let spending = |amount: u64| {
+            let approve = crate::erc20::ApproveCall {
+                spender: Address::repeat_byte(2),
+                amount: U256::from(amount),
+            };
+            let tx = tx(token, ethers::abi::AbiEncode::encode(approve));
+            let decoded = decode(&mainnet(), &tx);
+            Spending {
+                recipient: decoded.recipient,
+                spends: Vec::new(),
+                approval: decoded.approval,
+                unknown_token: None,
+                nfts: Vec::new(),
+            }
+        };
+        let mut ledger = Ledger::default();
+        let revocation = spending(0);
+        assert!(
+            check(&policy, "mainnet", &contacts, &revocation, &ledger, 0)
+                .is_ok()
+        );
+        let approval = spending(1_000);
+        assert!(check(&policy, "mainnet", &contacts, &approval, &ledger, 0)
+            .is_err());
+        // the allowances are never recorded as spent.
+        ledger.record("mainnet", &approval, 0);
+        assert!(ledger.entries.is_empty());
+    }
+
+    #[test]
+    fn contract_calls() {
+        let contract = Address::repeat_byte(7);
+        let call = calldata("function claim()", &[]);
+        let decoded = decode(&mainnet(), &tx(contract, call));
+        assert_eq!(decoded.recipient, Some(contract));
+        assert_eq!(decoded.token_amount, None);
+    }
+
+    #[test]
+    fn nft_transfers() {
+        let contract = Address::repeat_byte(7);
+        let batch = crate::erc1155::SafeBatchTransferFromCall {
+            from: Address::repeat_byte(1),
+            to: Address::repeat_byte(2),
+            ids: vec![U256::from(3), U256::from(4)],
+            amounts: vec![U256::from(5), U256::from(6)],
+            data: Default::default(),
+        };
+        let tx = tx(contract, ethers::abi::AbiEncode::encode(batch));
+        let decoded = decode(&mainnet(), &tx);
+        assert_eq!(decoded.recipient, Some(Address::repeat_byte(2)));
+        assert_eq!(
+            decoded.nfts,
+            vec![
+                NftTransfer {
+                    contract,
+                    token_id: U256::from(3),
+                    amount: U256::from(5),
+                },
+                NftTransfer {
+                    contract,
+                    token_id: U256::from(4),
+                    amount: U256::from(6),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn wrapped_native_token() {
+        let network = mainnet();
+        let weth = network.wrapped_native_token.unwrap();
+        let deposit =
+            ethers::abi::AbiEncode::encode(crate::weth::DepositCall {});
+        let mut tx = tx(weth, deposit);
+        tx.set_value(ethers::utils::parse_ether("1").unwrap());
+        let decoded = decode(&network, &tx);
+        assert_eq!(decoded.recipient, None);
+        assert_eq!(
+            decoded.value,
+            Some(ethers::utils::parse_ether("1").unwrap())
+        );
+        // other contracts are not exempted.
+        tx.set_to(Address::repeat_byte(7));
+        assert_eq!(
+            decode(&network, &tx).recipient,
+            Some(Address::repeat_byte(7))
+        );
+    }
+
+    #[test]
+    fn token_limits_from_config() {
+        let policy: NetworkPolicy = serde_json::from_value(serde_json::json!({
+            "tokens": {
+                "0x2791bca1f2de4661ed88a30c99a7a9449aa84174": {
+                    "max_per_transaction": "100"
+                }
+            }
+        }))
+        .unwrap();
+        let token: Address = "0x2791bca1f2de4661ed88a30c99a7a9449aa84174"
+            .parse()
+            .unwrap();
+        let spend = Spend {
+            token: Some(token),
+            amount: U256::from(100_500_000u64),
+            symbol: "USDC".into(),
+            decimals: 6,
+        };
+        let spending = Spending {
+            recipient: None,
+            spends: vec![spend],
+            approval: None,
+            unknown_token: None,
+            nfts: Vec::new(),
+        };
+        let err =
+            check(&policy, "polygon", &[], &spending, &Ledger::default(), 0)
+                .unwrap_err();
+        assert!(err.to_string().contains("100.5 USDC"));
+        // the transfers of tokens with limits are rejected when their
+        // decimals are unknown.
+        let spending = Spending {
+            unknown_token: Some((token, U256::one())),
+            ..Default::default()
+        };
+        assert!(check(
+            &policy,
+            "polygon",
+            &[],
+            &spending,
+            &Ledger::default(),
+            0
+        )
+        .is_err());
+        let spending = Spending {
+            unknown_token: Some((Address::repeat_byte(9), U256::one())),
+            ..Default::default()
+        };
+        assert!(check(
+            &policy,
+            "polygon",
+            &[],
+            &spending,
+            &Ledger::default(),
+            0
+        )
+        .is_ok());
+    }
+}
@@ -0,0 +1,170 @@
+//! USD prices from the Chainlink price feeds configured for the network,
+//! on-chain, so no centralized price API is queried.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use ethers::types::{Address, U256};
+
+use crate::chainlink::AggregatorV3;
+use crate::config::Network;
+use crate::state::EthersClient;
+
+/// Prices not updated for longer than this are ignored, the feeds are
+/// updated at least daily.
+const MAX_PRICE_AGE: u64 = 25 * 60 * 60;
+
+/// Prices not updated for longer than this are shown as stale, the most
+/// used feeds are updated at least hourly.
+const STALE_PRICE_AGE: u64 = 60 * 60;
+
+/// A USD price, with its decimals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Price {
+    pub answer: U256,
+    pub decimals: u8,
+    /// UNIX timestamp of the last update, in seconds.
+    pub updated_at: u64,
+}
+
+impl Price {
+    /// Fetch the latest price of the feed, `None` if the feed is not
+    /// available or the price is stale.
+    ///
+    /// The results are cached for the session.
+    pub async fn fetch(client: &EthersClient, feed: Address) -> Option<Self> {
+        if let Some(price) = cache().lock().ok()?.get(&feed) {
+            return *price;
+        }
+        let price = Self::fetch_uncached(client, feed).await;
+        cache().lock().ok()?.insert(feed, price);
+        price
+    }
+
+    async fn fetch_uncached(
+        client: &EthersClient,
+        feed: Address,
+    ) -> Option<Self> {
+        let aggregator = AggregatorV3::new(feed, Arc::new(client.clone()));
+        let decimals = aggregator.decimals().call().await.ok()?;
+        let (_, answer, _, updated_at, _) =
+            aggregator.latest_round_data().call().await.ok()?;
+        let updated_at = updated_at.low_u64();
+        let age = crate::policy::now().saturating_sub(updated_at);
+        if answer.is_negative() || answer.is_zero() || age > MAX_PRICE_AGE {
+            return None;
+        }
+        Some(Self {
+            answer: answer.into_raw(),
+            decimals,
+            updated_at,
+        })
+    }
+
+    /// The USD value of the amount, formatted as ` (~$1234.56)` to be
+    /// appended to the displayed amount, empty on overflow.
+    ///
+    /// Stale prices are marked, e.g. ` (~$1234.56, stale price)`.
+    pub fn suffix(&self, amount: U256, decimals: u8) -> String {
+        match self.usd(amount, decimals) {
+            Some(usd) if self.is_stale(crate::policy::now()) => {
+                format!(" (~{usd}, stale price)")
+            }
+            Some(usd) => format!(" (~{usd})"),
+            None => String::new(),
+        }
+    }
+
+    /// Returns `true` if the price was not updated for an hour at `now`.
+    pub fn is_stale(&self, now: u64) -> bool {
+        now.saturating_sub(self.updated_at) > STALE_PRICE_AGE
+    }
+
+    /// The USD value of the amount, e.g. `$1234.56`, `None` on overflow.
+    pub fn usd(&self, amount: U256, decimals: u8) -> Option<String> {
+        // value in cents = amount * answer / 10^(decimals + price decimals - 2)
+        let scale = U256::exp10(decimals as usize + self.decimals as usize);
+        let cents =
+            amount.checked_mul(self.answer)?.checked_mul(100.into())? / scale;
+        Some(format!("${}.{:02}", cents / 100, (cents % 100).low_u64()))
+    }
+}
+
+fn cache() -> &'static Mutex<HashMap<Address, Option<Price>>> {
+    static CACHE: OnceLock<Mutex<HashMap<Address, Option<Price>>>> =
+        OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+/// The price feed of the token, or of the native currency if `None`.
+pub fn feed(network: &Network, token: Option<Address>) -> Option<Address> {
+    match token {
+        Some(token) => network.price_feeds.tokens.get(&token).copied(),
+        None => network.price_feeds.native,
+    }
+}
+
+/// The USD value of the amount, formatted as ` (~$1234.56)` to be appended
+/// to the displayed amount, empty if there is no price feed.
+pub async fn usd_suffix(
+    client: &EthersClient,
+    network: &Network,
+    token: Option<Address>,
+    amount: U256,
+    decimals: u8,
+) -> String {
+    match token_price(client, network, token).await {
+        Some(price) => price.suffix(amount, decimals),
+        None => String::new(),
+    }
+}
+
+/// The price of the token, or of the native currency if `None`.
+pub async fn token_price(
+    client: &EthersClient,
+    network: &Network,
+    token: Option<Address>,
+) -> Option<Price> {
+    match feed(network, token) {
+        Some(feed) => Price::fetch(client, feed).await,
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usd_values() {
+        // 1850.12345678 USD, with 8 decimals.
+        let price = Price {
+            answer: U256::from(185_012_345_678u64),
+            decimals: 8,
+            updated_at: 1_000_000,
+        };
+        let amount = ethers::utils::parse_ether("1.5").unwrap();
+        assert_eq!(price.usd(amount, 18).unwrap(), "$2775.18");
+        assert_eq!(price.usd(U256::from(1), 18).unwrap(), "$0.00");
+        assert_eq!(price.usd(U256::from(2_500_000), 6).unwrap(), "$4625.30");
+        assert!(price.usd(U256::MAX, 18).is_none());
+        assert!(!price.is_stale(1_000_000 + 60 * 60));
+        assert!(price.is_stale(1_000_000 + 60 * 60 + 1));
+    }
+
+    #[test]
+    fn feeds() {
+        let mut network = crate::config::Config::default()
+            .networks
+            .remove("mainnet")
+            .unwrap();
+        let token = Address::repeat_byte(1);
+        network
+            .price_feeds
+            .tokens
+            .insert(token, Address::repeat_byte(2));
+        assert!(feed(&network, None).is_some());
+        assert_eq!(feed(&network, Some(token)), Some(Address::repeat_byte(2)));
+        assert_eq!(feed(&network, Some(Address::zero())), None);
+    }
+}
@@ -0,0 +1,256 @@
+//! A human readable summary of a transaction receipt, with its decoded
+//! token events.
+
+use ethers::contract::EthEvent;
+use ethers::types::{Address, Log, TransactionReceipt, U256, U64};
+
+use crate::decoder::TokenInfo;
+use crate::erc20::{ApprovalFilter, TransferFilter};
+
+/// A `Transfer` or `Approval` event, of an ERC20 or an ERC721 token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenEvent {
+    Transfer {
+        token: Address,
+        from: Address,
+        to: Address,
+        value: TokenValue,
+    },
+    Approval {
+        token: Address,
+        owner: Address,
+        spender: Address,
+        value: TokenValue,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenValue {
+    /// An ERC20 amount, not indexed.
+    Amount(U256),
+    /// An ERC721 token id, indexed.
+    TokenId(U256),
+}
+
+impl TokenEvent {
+    pub fn token(&self) -> Address {
+        match self {
+            Self::Transfer { token, .. } | Self::Approval { token, .. } => {
+                *token
+            }
+        }
+    }
+
+    /// The accounts involved in the event.
+    pub fn addresses(&self) -> [Address; 2] {
+        match self {
+            Self::Transfer { from, to, .. } => [*from, *to],
+            Self::Approval { owner, spender, .. } => [*owner, *spender],
+        }
+    }
+
+    /// Describes the event, the amounts are formatted using the token info
+    /// and the addresses using `label`.
+    pub fn describe(
+        &self,
+        info: Option<&TokenInfo>,
+        label: impl Fn(Address) -> String,
+    ) -> String {
+        let token = ethers::utils::to_checksum(&self.token(), None);
+        let value = |value: &TokenValue| match (value, info) {
+            (TokenValue::Amount(amount), Some(info)) => {
+                let amount = if *amount == U256::MAX {
+                    "unlimited".to_string()
+                } else {
+                    crate::history::format_units(*amount, info.decimals)
+                };
+                format!("{} {}", amount, info.symbol)
+            }
+            (TokenValue::Amount(amount), None) => {
+                let amount = if *amount == U256::MAX {
+                    "unlimited".to_string()
+                } else {
+                    amount.to_string()
+                };
+                format!("{} of {}", amount, token)
+            }
+            (TokenValue::TokenId(id), _) => {
+                format!("token #{} of {}", id, token)
+            }
+        };
+        match self {
+            Self::Transfer {
+                from, to, value: v, ..
+            } => format!(
+                "Transfer {} from {} to {}",
+                value(v),
+                label(*from),
+                label(*to)
+            ),
+            Self::Approval {
+                owner,
+                spender,
+                value: v,
+                ..
+            } => format!(
+                "Approval of {} from {} to {}",
+                value(v),
+                label(*owner),
+                label(*spender)
+            ),
+        }
+    }
+}
+
+/// Decodes the `Transfer` and `Approval` events of the logs, the other
+/// events are ignored.
+pub fn decode_events(logs: &[Log]) -> Vec<TokenEvent> {
+    logs.iter().filter_map(decode_event).collect()
+}
+
+fn decode_event(log: &Log) -> Option<TokenEvent> {
+    let value = match log.topics.len() {
+        3 if log.data.len() == 32 => {
+            TokenValue::Amount(U256::from_big_endian(&log.data))
+        }
+        4 => {
+            TokenValue::TokenId(U256::from_big_endian(log.topics[3].as_bytes()))
+        }
+        _ => return None,
+    };
+    let token = log.address;
+    let first = Address::from(log.topics[1]);
+    let second = Address::from(log.topics[2]);
+    if log.topics[0] == TransferFilter::signature() {
+        Some(TokenEvent::Transfer {
+            token,
+            from: first,
+            to: second,
+            value,
+        })
+    } else if log.topics[0] == ApprovalFilter::signature() {
+        Some(TokenEvent::Approval {
+            token,
+            owner: first,
+            spender: second,
+            value,
+        })
+    } else {
+        None
+    }
+}
+
+/// The status, block, gas and fee lines of the receipt.
+pub fn summary(
+    receipt: &TransactionReceipt,
+    currency_symbol: &str,
+) -> Vec<String> {
+    let status = match receipt.status {
+        Some(status) if status == U64::one() => "Success",
+        Some(_) => "Failed (reverted)",
+        None => "Unknown",
+    };
+    let mut lines = vec![
+        format!("Status: {status}"),
+        format!("Transaction: {:?}", receipt.transaction_hash),
+    ];
+    if let Some(block) = receipt.block_number {
+        lines.push(format!("Block: {block}"));
+    }
+    if let Some(gas_used) = receipt.gas_used {
+        lines.push(format!("Gas used: {gas_used}"));
+    }
+    if let Some(price) = receipt.effective_gas_price {
+        let gwei = crate::history::format_units(price, 9);
+        lines.push(format!("Effective gas price: {gwei} gwei"));
+    }
+    if let (Some(gas_used), Some(price)) =
+        (receipt.gas_used, receipt.effective_gas_price)
+    {
+        let fee =
+            crate::history::format_units(gas_used.saturating_mul(price), 18);
+        lines.push(format!("Total fee: {fee} {currency_symbol}"));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::H256;
+
+    #[test]
+    fn receipt_summary() {
+        let receipt = TransactionReceipt {
+            status: Some(U64::one()),
+            block_number: Some(100.into()),
+            gas_used: Some(21_000.into()),
+            effective_gas_price: Some(U256::from(1_500_000_000u64)),
+            ..Default::default()
+        };
+        let lines = summary(&receipt, "ETH");
+        assert_eq!(lines[0], "Status: Success");
+        assert_eq!(lines[2], "Block: 100");
+        assert_eq!(lines[4], "Effective gas price: 1.5 gwei");
+        assert_eq!(lines[5], "Total fee: 0.0000315 ETH");
+    }
+
+    #[test]
+    fn token_events() {
+        let from = Address::repeat_byte(1);
+        let to = Address::repeat_byte(2);
+        let transfer = Log {
+            address: Address::repeat_byte(5),
+            topics: vec![
+                TransferFilter::signature(),
+                H256::from(from),
+                H256::from(to),
+            ],
+            data: ethers::abi::encode(&[ethers::abi::Token::Uint(U256::from(
+                1_500_000,
+            ))])
+            .into(),
+            ..Default::default()
+        };
+        let approval = Log {
+            topics: vec![
+                ApprovalFilter::signature(),
+                H256::from(from),
+                H256::from(to),
+            ],
+            data: ethers::abi::encode(&[ethers::abi::Token::Uint(U256::MAX)])
+                .into(),
+            ..transfer.clone()
+        };
+        let nft = Log {
+            topics: vec![
+                TransferFilter::signature(),
+                H256::from(from),
+                H256::from(to),
+                H256::from_low_u64_be(7),
+            ],
+            data: Default::default(),
+            ..transfer.clone()
+        };
+        let events = decode_events(&[transfer, approval, nft]);
+        assert_eq!(events.len(), 3);
+        let info = TokenInfo {
+            symbol: "USDC".into(),
+            decimals: 6,
+        };
+        let label = |address: Address| {
+            if address == from { "Me" } else { "Friend" }.to_string()
+        };
+        assert_eq!(
+            events[0].describe(Some(&info), label),
+            "Transfer 1.5 USDC from Me to Friend"
+        );
+        assert_eq!(
+            events[1].describe(Some(&info), label),
+            "Approval of unlimited USDC from Me to Friend"
+        );
+        assert!(events[2]
+            .describe(None, label)
+            .starts_with("Transfer token #7 of 0x0505"));
+    }
+}
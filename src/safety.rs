@@ -0,0 +1,333 @@
+//! Pre-sign checks of the transfer recipient, to catch the common mistakes
+//! before the funds are gone for good.
+
+use std::collections::BTreeSet;
+
+use color_eyre::eyre;
+use color_eyre::Result;
+use ethers::contract::EthEvent;
+use ethers::providers::Middleware;
+use ethers::types::{Address, Filter, Log, H256, U256};
+
+use crate::config::{Contact, Network};
+use crate::erc20::TransferFilter;
+use crate::state::EthersClient;
+
+/// What is being sent to the recipient.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Asset {
+    /// The network native currency.
+    Native,
+    /// Tokens of the given contract (ERC20, ERC721 or ERC1155).
+    Token(Address),
+}
+
+/// The on-chain state of the recipient.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct RecipientInfo {
+    is_contract: bool,
+    nonce: U256,
+    balance: U256,
+}
+
+impl RecipientInfo {
+    async fn fetch(client: &EthersClient, recipient: Address) -> Result<Self> {
+        let code = client.get_code(recipient, None).await.map_err(|e| {
+            eyre::eyre!("Failed to fetch recipient code: {}", e)
+        })?;
+        let nonce = client
+            .get_transaction_count(recipient, None)
+            .await
+            .map_err(|e| {
+                eyre::eyre!("Failed to fetch recipient nonce: {}", e)
+            })?;
+        let balance =
+            client.get_balance(recipient, None).await.map_err(|e| {
+                eyre::eyre!("Failed to fetch recipient balance: {}", e)
+            })?;
+        Ok(Self {
+            is_contract: !code.is_empty(),
+            nonce,
+            balance,
+        })
+    }
+
+    /// An address without code, transactions or balance.
+    fn is_unused(&self) -> bool {
+        !self.is_contract && self.nonce.is_zero() && self.balance.is_zero()
+    }
+}
+
+/// Checks the recipient of a transfer, returning the warnings to show
+/// before signing.
+pub async fn check_recipient(
+    client: &EthersClient,
+    network: &Network,
+    contacts: &[Contact],
+    recipient: Address,
+    asset: Asset,
+) -> Result<Vec<String>> {
+    let info = RecipientInfo::fetch(client, recipient).await?;
+    Ok(warnings(network, contacts, recipient, asset, &info))
+}
+
+fn warnings(
+    network: &Network,
+    contacts: &[Contact],
+    recipient: Address,
+    asset: Asset,
+    info: &RecipientInfo,
+) -> Vec<String> {
+    let mut warnings = Vec::new();
+    // the zero address could be a contact ("Burn"), so always warn.
+    if recipient.is_zero() {
+        warnings.push(
+            "Recipient is the zero address, the funds will be burned"
+                .to_string(),
+        );
+        return warnings;
+    }
+    match asset {
+        Asset::Token(token) if token == recipient => warnings.push(
+            "Recipient is the token contract itself, tokens sent to it are \
+             most likely lost"
+                .to_string(),
+        ),
+        Asset::Native if info.is_contract => warnings.push(format!(
+            "Recipient is a contract, make sure it can receive {}",
+            network.currency_symbol
+        )),
+        _ => {}
+    }
+    let is_contact = contacts.iter().any(|c| c.address == recipient);
+    if !is_contact && info.is_unused() {
+        warnings.push(
+            "Recipient has never been used and is not in your contacts, \
+             double check the address"
+                .to_string(),
+        );
+    }
+    warnings
+}
+
+/// How many hex characters, at the start and at the end of an address, are
+/// compared to find lookalikes. Most wallets and explorers only show these.
+const LOOKALIKE_CHARS: usize = 4;
+
+/// How many blocks back to scan for the recent transfers.
+const POISONING_SCAN_BLOCKS: u64 = 10_000;
+
+/// Returns the contacts that look like the recipient, i.e. they share the
+/// first and last characters but differ in the middle, which is what the
+/// address poisoning attacks rely on.
+pub fn lookalike_contacts(
+    recipient: Address,
+    contacts: &[Contact],
+) -> Vec<&Contact> {
+    contacts
+        .iter()
+        .filter(|c| is_lookalike(recipient, c.address))
+        .collect()
+}
+
+fn is_lookalike(a: Address, b: Address) -> bool {
+    a != b && edges(&hex::encode(a)) == edges(&hex::encode(b))
+}
+
+fn edges(address_hex: &str) -> (&str, &str) {
+    let len = address_hex.len();
+    (
+        &address_hex[..LOOKALIKE_CHARS],
+        &address_hex[len - LOOKALIKE_CHARS..],
+    )
+}
+
+/// The counterparties of the owner recent ERC20 transfers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecentTransfers {
+    /// The counterparties of zero-value transfers, which are used by
+    /// attackers to plant lookalike addresses in the owner history.
+    pub zero_value: BTreeSet<Address>,
+    /// The accounts the owner actually sent tokens to.
+    pub recipients: BTreeSet<Address>,
+}
+
+impl RecentTransfers {
+    /// Scans the recent transfers from and to the owner.
+    pub async fn scan(client: &EthersClient, owner: Address) -> Result<Self> {
+        let latest = client
+            .get_block_number()
+            .await
+            .map_err(|e| eyre::eyre!("Failed to fetch block number: {}", e))?
+            .as_u64();
+        let from_block = latest.saturating_sub(POISONING_SCAN_BLOCKS);
+        let owner_topic = H256::from(owner);
+        let mut transfers = Self::default();
+        for (from, to) in crate::logscan::chunks(from_block, latest) {
+            let filter = Filter::new()
+                .from_block(from)
+                .to_block(to)
+                .topic0(TransferFilter::signature());
+            for filter in [
+                filter.clone().topic1(owner_topic),
+                filter.topic2(owner_topic),
+            ] {
+                let logs = client.get_logs(&filter).await.map_err(|e| {
+                    eyre::eyre!("Failed to fetch transfer logs: {}", e)
+                })?;
+                for log in &logs {
+                    transfers.add(owner, log);
+                }
+            }
+        }
+        Ok(transfers)
+    }
+
+    fn add(&mut self, owner: Address, log: &Log) {
+        // ERC721 transfers have the same signature, but with 4 topics.
+        if log.topics.len() != 3 || log.data.len() != 32 {
+            return;
+        }
+        let from = Address::from(log.topics[1]);
+        let to = Address::from(log.topics[2]);
+        if log.data.iter().all(|b| *b == 0) {
+            self.zero_value
+                .extend([from, to].into_iter().filter(|a| *a != owner));
+        } else if from == owner && to != owner {
+            self.recipients.insert(to);
+        }
+    }
+
+    /// The past recipients that look like the recipient, but are different
+    /// addresses.
+    pub fn lookalike_recipients(&self, recipient: Address) -> Vec<Address> {
+        self.recipients
+            .iter()
+            .copied()
+            .filter(|r| is_lookalike(recipient, *r))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn network() -> Network {
+        crate::config::Config::default()
+            .networks
+            .remove("mainnet")
+            .unwrap()
+    }
+
+    #[test]
+    fn zero_address() {
+        let contacts = crate::config::Config::default().contacts;
+        let warnings = warnings(
+            &network(),
+            &contacts,
+            Address::zero(),
+            Asset::Native,
+            &RecipientInfo::default(),
+        );
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("zero address"));
+    }
+
+    #[test]
+    fn token_contract_itself() {
+        let token = Address::repeat_byte(1);
+        let info = RecipientInfo {
+            is_contract: true,
+            nonce: U256::one(),
+            ..Default::default()
+        };
+        let warnings =
+            warnings(&network(), &[], token, Asset::Token(token), &info);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("token contract itself"));
+    }
+
+    #[test]
+    fn native_to_contract() {
+        let info = RecipientInfo {
+            is_contract: true,
+            ..Default::default()
+        };
+        let recipient = Address::repeat_byte(1);
+        let warnings =
+            warnings(&network(), &[], recipient, Asset::Native, &info);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("is a contract"));
+    }
+
+    #[test]
+    fn lookalikes() {
+        let contact: Address = "0x1234567890abcdef1234567890abcdef1234abcd"
+            .parse()
+            .unwrap();
+        let lookalike: Address = "0x123400000000000000000000000000000000abcd"
+            .parse()
+            .unwrap();
+        let contacts = vec![Contact {
+            name: "Friend".into(),
+            address: contact,
+        }];
+        let found = lookalike_contacts(lookalike, &contacts);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].name, "Friend");
+        assert!(lookalike_contacts(contact, &contacts).is_empty());
+        let different: Address = "0x123500000000000000000000000000000000abcd"
+            .parse()
+            .unwrap();
+        assert!(lookalike_contacts(different, &contacts).is_empty());
+    }
+
+    #[test]
+    fn recent_transfers() {
+        let owner = Address::repeat_byte(1);
+        let friend: Address = "0x1234567890abcdef1234567890abcdef1234abcd"
+            .parse()
+            .unwrap();
+        let poisoner: Address = "0x123400000000000000000000000000000000abcd"
+            .parse()
+            .unwrap();
+        let log = |from: Address, to: Address, amount: u64| Log {
+            topics: vec![
+                TransferFilter::signature(),
+                H256::from(from),
+                H256::from(to),
+            ],
+            data: ethers::abi::encode(&[ethers::abi::Token::Uint(
+                amount.into(),
+            )])
+            .into(),
+            ..Default::default()
+        };
+        let mut transfers = RecentTransfers::default();
+        transfers.add(owner, &log(owner, friend, 1_000));
+        transfers.add(owner, &log(owner, poisoner, 0));
+        // received transfers are not recipients.
+        transfers.add(owner, &log(poisoner, owner, 1_000));
+        assert_eq!(transfers.zero_value, BTreeSet::from([poisoner]));
+        assert_eq!(transfers.recipients, BTreeSet::from([friend]));
+        assert_eq!(transfers.lookalike_recipients(poisoner), vec![friend]);
+        assert!(transfers.lookalike_recipients(friend).is_empty());
+    }
+
+    #[test]
+    fn unused_address() {
+        let recipient = Address::repeat_byte(2);
+        let info = RecipientInfo::default();
+        let found = warnings(&network(), &[], recipient, Asset::Native, &info);
+        assert_eq!(found.len(), 1);
+        assert!(found[0].contains("never been used"));
+        let contacts = vec![Contact {
+            name: "Friend".into(),
+            address: recipient,
+        }];
+        let found =
+            warnings(&network(), &contacts, recipient, Asset::Native, &info);
+        assert!(found.is_empty());
+    }
+}
@@ -0,0 +1,471 @@
+//! Transaction simulation, used to show the expected balance changes and
+//! any revert reason before signing.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use color_eyre::Result;
+use ethers::abi::{AbiDecode, ParamType, Token};
+use ethers::contract::EthEvent;
+use ethers::providers::{HttpClientError, JsonRpcClient, ProviderError};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, Bytes, H256, I256, U256};
+use serde::Deserialize;
+
+use crate::erc20::{Erc20Calls, TransferFilter};
+use crate::state::EthersClient;
+
+/// The result of simulating a transaction against the latest block.
+#[derive(Debug, Clone, Default)]
+pub struct Simulation {
+    /// `true` if the changes were traced by the node, otherwise they are
+    /// derived from the transaction calldata.
+    pub traced: bool,
+    /// `Some` if the transaction would revert, with the decoded reason.
+    pub revert_reason: Option<String>,
+    /// Native balance changes per account.
+    pub native_changes: BTreeMap<Address, I256>,
+    /// ERC20 balance changes per (token, account).
+    pub token_changes: BTreeMap<(Address, Address), I256>,
+}
+
+impl Simulation {
+    fn add_native(&mut self, from: Address, to: Address, value: U256) {
+        let value = I256::from_raw(value);
+        *self.native_changes.entry(from).or_default() -= value;
+        *self.native_changes.entry(to).or_default() += value;
+    }
+
+    fn add_token(
+        &mut self,
+        token: Address,
+        from: Address,
+        to: Address,
+        value: U256,
+    ) {
+        let value = I256::from_raw(value);
+        *self.token_changes.entry((token, from)).or_default() -= value;
+        *self.token_changes.entry((token, to)).or_default() += value;
+    }
+
+    /// Human readable lines of the balance changes, token amounts are
+    /// formatted using their on-chain symbol and decimals.
+    ///
+    /// The accounts are labeled with their reverse ENS names if `ens_labels`
+    /// is set.
+    pub async fn describe(
+        &self,
+        client: &EthersClient,
+        network: &crate::config::Network,
+        sender: Address,
+        ens_labels: bool,
+    ) -> Vec<String> {
+        let mut names = BTreeMap::new();
+        if ens_labels {
+            let accounts = self
+                .native_changes
+                .keys()
+                .chain(self.token_changes.keys().map(|(_, account)| account));
+            for account in accounts {
+                if let Some(name) = crate::ens::lookup(client, *account).await {
+                    names.insert(*account, name);
+                }
+            }
+        }
+        let label = |address: &Address| {
+            let label = crate::ens::label(
+                *address,
+                names.get(address).map(String::as_str),
+            );
+            if *address == sender {
+                format!("{label} (You)")
+            } else {
+                label
+            }
+        };
+        let mut lines = Vec::new();
+        for (address, change) in &self.native_changes {
+            if change.is_zero() {
+                continue;
+            }
+            let amount = ethers::utils::format_units(*change, "ether")
+                .unwrap_or_else(|_| change.to_string());
+            let usd = crate::price::usd_suffix(
+                client,
+                network,
+                None,
+                change.into_sign_and_abs().1,
+                18,
+            )
+            .await;
+            lines.push(format!(
+                "{}: {} {}{}",
+                label(address),
+                signed(amount, change),
+                network.currency_symbol,
+                usd
+            ));
+        }
+        let client = Arc::new(client.clone());
+        for ((token, address), change) in &self.token_changes {
+            if change.is_zero() {
+                continue;
+            }
+            let contract = crate::erc20::Erc20::new(*token, client.clone());
+            let symbol = match contract.symbol().call().await {
+                Ok(symbol) => symbol,
+                Err(_) => ethers::utils::to_checksum(token, None),
+            };
+            let (amount, usd) = match contract.decimals().call().await {
+                Ok(decimals) => (
+                    ethers::utils::format_units(*change, decimals as u32)
+                        .unwrap_or_else(|_| change.to_string()),
+                    crate::price::usd_suffix(
+                        &client,
+                        network,
+                        Some(*token),
+                        change.into_sign_and_abs().1,
+                        decimals,
+                    )
+                    .await,
+                ),
+                Err(_) => (change.to_string(), String::new()),
+            };
+            lines.push(format!(
+                "{}: {} {}{}",
+                label(address),
+                signed(amount, change),
+                symbol,
+                usd
+            ));
+        }
+        lines
+    }
+}
+
+fn signed(amount: String, change: &I256) -> String {
+    if change.is_positive() {
+        format!("+{amount}")
+    } else {
+        amount
+    }
+}
+
+/// Simulate the transaction using `debug_traceCall`, falling back to
+/// `eth_call` when the node does not support tracing.
+pub async fn simulate(
+    client: &EthersClient,
+    tx: &TypedTransaction,
+) -> Result<Simulation> {
+    match trace_call(client, tx).await {
+        Ok(simulation) => Ok(simulation),
+        Err(_) => call(client, tx).await,
+    }
+}
+
+async fn trace_call(
+    client: &EthersClient,
+    tx: &TypedTransaction,
+) -> Result<Simulation> {
+    let options = serde_json::json!({
+        "tracer": "callTracer",
+        "tracerConfig": { "withLog": true },
+    });
+    let frame: CallFrame = client
+        .as_ref()
+        .request("debug_traceCall", (tx, "latest", options))
+        .await?;
+    let mut simulation = Simulation {
+        traced: true,
+        ..Default::default()
+    };
+    if let Some(error) = &frame.error {
+        simulation.revert_reason = Some(
+            frame
+                .revert_reason
+                .clone()
+                .or_else(|| {
+                    frame.output.as_ref().and_then(|o| decode_revert_reason(o))
+                })
+                .unwrap_or_else(|| error.clone()),
+        );
+    } else {
+        frame.apply(&mut simulation);
+    }
+    Ok(simulation)
+}
+
+/// Fallback for nodes without tracing, the sender balance is overridden so
+/// only the contract logic could make the call revert, and the changes are
+/// derived from the transaction itself.
+///
+/// The call is retried without the override if the node rejects it.
+async fn call(
+    client: &EthersClient,
+    tx: &TypedTransaction,
+) -> Result<Simulation> {
+    let from = tx.from().copied().unwrap_or_default();
+    let overrides = serde_json::json!({
+        format!("{from:?}"): { "balance": U256::MAX >> 1 },
+    });
+    let result = match client
+        .as_ref()
+        .request::<_, Bytes>("eth_call", (tx, "latest", overrides))
+        .await
+    {
+        Err(HttpClientError::JsonRpcError(e))
+            if revert_reason(&e.message, e.data.as_ref()).is_none() =>
+        {
+            client
+                .as_ref()
+                .request::<_, Bytes>("eth_call", (tx, "latest"))
+                .await
+        }
+        result => result,
+    };
+    let mut simulation = Simulation::default();
+    match result {
+        Ok(_) => {}
+        Err(HttpClientError::JsonRpcError(e)) => {
+            match revert_reason(&e.message, e.data.as_ref()) {
+                Some(reason) => {
+                    simulation.revert_reason = Some(reason);
+                    return Ok(simulation);
+                }
+                None => return Err(HttpClientError::JsonRpcError(e).into()),
+            }
+        }
+        Err(e) => return Err(e.into()),
+    }
+    let to = match tx.to_addr() {
+        Some(to) => *to,
+        None => return Ok(simulation),
+    };
+    if let Some(value) = tx.value().filter(|v| !v.is_zero()) {
+        simulation.add_native(from, to, *value);
+    }
+    let calldata = tx.data().map(|d| d.to_vec()).unwrap_or_default();
+    match Erc20Calls::decode(calldata) {
+        Ok(Erc20Calls::Transfer(call)) => {
+            simulation.add_token(to, from, call.recipient, call.amount);
+        }
+        Ok(Erc20Calls::TransferFrom(call)) => {
+            simulation.add_token(to, call.sender, call.recipient, call.amount);
+        }
+        _ => {}
+    }
+    Ok(simulation)
+}
+
+/// The revert reason of a failed `eth_call`, from its revert data or its
+/// message, `None` if the node rejected the request itself (e.g. the state
+/// overrides are not supported).
+fn revert_reason(
+    message: &str,
+    data: Option<&serde_json::Value>,
+) -> Option<String> {
+    let data = data
+        .and_then(|data| data.as_str())
+        .and_then(|data| data.parse::<Bytes>().ok())
+        .filter(|data| !data.is_empty());
+    if data.is_none() && !message.to_lowercase().contains("revert") {
+        return None;
+    }
+    let reason = data.and_then(|data| decode_revert_reason(&data));
+    Some(reason.unwrap_or_else(|| message.to_string()))
+}
+
+/// The revert reason of a failed request to the node, e.g. the gas
+/// estimation, `None` if it failed for another reason.
+pub fn provider_revert_reason(error: &ProviderError) -> Option<String> {
+    match error {
+        ProviderError::JsonRpcClientError(e) => {
+            match e.downcast_ref::<HttpClientError>() {
+                Some(HttpClientError::JsonRpcError(e)) => {
+                    revert_reason(&e.message, e.data.as_ref())
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Decode the standard `Error(string)` and `Panic(uint256)` revert data.
+pub fn decode_revert_reason(data: &[u8]) -> Option<String> {
+    const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+    const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+    if data.len() < 4 {
+        return None;
+    }
+    let (selector, data) = data.split_at(4);
+    if selector == ERROR_SELECTOR {
+        match ethers::abi::decode(&[ParamType::String], data)
+            .ok()?
+            .pop()?
+        {
+            Token::String(reason) => Some(reason),
+            _ => None,
+        }
+    } else if selector == PANIC_SELECTOR {
+        match ethers::abi::decode(&[ParamType::Uint(256)], data)
+            .ok()?
+            .pop()?
+        {
+            Token::Uint(code) => Some(format!("Panic(0x{code:x})")),
+            _ => None,
+        }
+    } else {
+        None
+    }
+}
+
+/// A call frame, as returned by geth's `callTracer`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CallFrame {
+    #[serde(rename = "type")]
+    kind: String,
+    from: Address,
+    #[serde(default)]
+    to: Option<Address>,
+    #[serde(default)]
+    value: Option<U256>,
+    #[serde(default)]
+    output: Option<Bytes>,
+    #[serde(default)]
+    error: Option<String>,
+    #[serde(default)]
+    revert_reason: Option<String>,
+    #[serde(default)]
+    calls: Vec<CallFrame>,
+    #[serde(default)]
+    logs: Vec<CallLog>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct CallLog {
+    address: Address,
+    topics: Vec<H256>,
+    data: Bytes,
+}
+
+impl CallFrame {
+    fn apply(&self, simulation: &mut Simulation) {
+        // reverted frames do not change any state.
+        if self.error.is_some() {
+            return;
+        }
+        let moves_value = !matches!(
+            self.kind.as_str(),
+            "DELEGATECALL" | "STATICCALL" | "CALLCODE"
+        );
+        match (self.to, self.value) {
+            (Some(to), Some(value)) if moves_value && !value.is_zero() => {
+                simulation.add_native(self.from, to, value);
+            }
+            _ => {}
+        }
+        for log in &self.logs {
+            // ERC721 transfers have the same signature, but with 4 topics.
+            if log.topics.len() != 3
+                || log.topics[0] != TransferFilter::signature()
+                || log.data.len() != 32
+            {
+                continue;
+            }
+            simulation.add_token(
+                log.address,
+                Address::from(log.topics[1]),
+                Address::from(log.topics[2]),
+                U256::from_big_endian(&log.data),
+            );
+        }
+        for call in &self.calls {
+            call.apply(simulation);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn revert_reasons() {
+        let data = ethers::abi::encode(&[Token::String("Not enough".into())]);
+        let data = [&[0x08, 0xc3, 0x79, 0xa0], &data[..]].concat();
+        assert_eq!(decode_revert_reason(&data).unwrap(), "Not enough");
+        let data = ethers::abi::encode(&[Token::Uint(0x11.into())]);
+        let data = [&[0x4e, 0x48, 0x7b, 0x71], &data[..]].concat();
+        assert_eq!(decode_revert_reason(&data).unwrap(), "Panic(0x11)");
+        assert!(decode_revert_reason(&[0xde, 0xad]).is_none());
+    }
+
+    #[test]
+    fn call_errors() {
+        let data = ethers::abi::encode(&[Token::String("Not enough".into())]);
+        let data = [&[0x08, 0xc3, 0x79, 0xa0], &data[..]].concat();
+        let data = serde_json::json!(Bytes::from(data));
+        assert_eq!(
+            revert_reason("execution reverted", Some(&data)).unwrap(),
+            "Not enough"
+        );
+        assert_eq!(
+            revert_reason("execution reverted", None).unwrap(),
+            "execution reverted"
+        );
+        let custom = serde_json::json!("0xdeadbeef");
+        assert_eq!(
+            revert_reason("VM Exception", Some(&custom)).unwrap(),
+            "VM Exception"
+        );
+        // the requests rejected by the node are not reverts.
+        assert!(revert_reason("invalid params", None).is_none());
+        let empty = serde_json::json!("0x");
+        assert!(
+            revert_reason("overrides not supported", Some(&empty)).is_none()
+        );
+    }
+
+    #[test]
+    fn trace_balance_changes() {
+        let sender = Address::repeat_byte(1);
+        let recipient = Address::repeat_byte(2);
+        let token = Address::repeat_byte(3);
+        let frame: CallFrame = serde_json::from_value(serde_json::json!({
+            "type": "CALL",
+            "from": sender,
+            "to": token,
+            "value": "0x0",
+            "logs": [{
+                "address": token,
+                "topics": [
+                    TransferFilter::signature(),
+                    H256::from(sender),
+                    H256::from(recipient),
+                ],
+                "data": H256::from_low_u64_be(100),
+            }],
+            "calls": [{
+                "type": "CALL",
+                "from": token,
+                "to": recipient,
+                "value": "0x10",
+                "error": "execution reverted",
+            }],
+        }))
+        .unwrap();
+        let mut simulation = Simulation::default();
+        frame.apply(&mut simulation);
+        assert!(simulation.native_changes.is_empty());
+        assert_eq!(
+            simulation.token_changes[&(token, sender)],
+            I256::from(-100)
+        );
+        assert_eq!(
+            simulation.token_changes[&(token, recipient)],
+            I256::from(100)
+        );
+    }
+}
@@ -45,10 +45,14 @@ impl AppState<WithIntial> {
             inquire::Select::new("Choose your network", networks).prompt()?;
         let network = self.config.networks.get(selected_network).cloned();
         if let Some(network) = network {
+            let network_name = selected_network.clone();
             Ok(AppState {
                 config: self.config,
                 term: self.term,
-                inner: WithNetwork { network },
+                inner: WithNetwork {
+                    network_name,
+                    network,
+                },
             })
         } else {
             Err(eyre::eyre!("Network not found"))
@@ -57,6 +61,8 @@ impl AppState<WithIntial> {
 }
 
 pub struct WithNetwork {
+    /// The network name in the config.
+    network_name: String,
     network: crate::config::Network,
 }
 
@@ -133,10 +139,27 @@ impl AppState<WithNetwork> {
             let address = Self::public_key_to_address(&public_key);
             accounts.push((address, public_key));
         }
+        let names = if ens_labels_enabled(&self.config, &self.inner.network) {
+            let client =
+                create_ethers_client(&self.config, &self.inner.network)?;
+            block_on(async {
+                let mut names = Vec::with_capacity(accounts.len());
+                for (address, _) in &accounts {
+                    names.push(crate::ens::lookup(&client, *address).await);
+                }
+                names
+            })
+        } else {
+            vec![None; accounts.len()]
+        };
         let accounts_display = accounts
             .iter()
+            .zip(&names)
             .enumerate()
-            .map(|(i, (address, _))| format!("{account} ({i}): {address}"))
+            .map(|(i, ((address, _), name))| {
+                let label = crate::ens::label(*address, name.as_deref());
+                format!("{account} ({i}): {label}")
+            })
             .collect::<Vec<_>>();
         let selected_account =
             inquire::Select::new("Select an account", accounts_display.clone())
@@ -157,6 +180,7 @@ impl AppState<WithNetwork> {
             config: self.config,
             term: self.term,
             inner: WithAccount {
+                network_name: self.inner.network_name,
                 network: self.inner.network,
                 address,
                 crypto_key_path,
@@ -193,22 +217,56 @@ impl AppState<WithNetwork> {
 pub struct WithAccount {
     address: types::Address,
     crypto_key_path: CryptoKeyPath,
+    network_name: String,
     network: crate::config::Network,
 }
 
 impl AppState<WithAccount> {
     pub fn ask_for_operation(self) -> Result<AppState<WithOperation>> {
         let network_native_token_symbol = &self.inner.network.currency_symbol;
-        let operations = vec![
+        let mut operations = vec![
             "Transfer ERC20 Tokens".into(),
             format!("Transfer {network_native_token_symbol}"),
             "Sign a message".into(),
+            "Sign typed data (EIP-712)".into(),
+            "Verify a signature".into(),
+            "Scan ERC20 allowances".into(),
+            "Call a contract function".into(),
+            "Call a view function".into(),
+            "Deploy a contract".into(),
+            "Transfer ERC721 NFT".into(),
+            "Transfer ERC1155 Tokens".into(),
         ];
+        if self.inner.network.wrapped_native_token.is_some() {
+            operations
+                .push(format!("Wrap or unwrap {network_native_token_symbol}"));
+        }
+        if self.inner.network.explorer_api_url.is_some() {
+            operations.push("Transactions history".into());
+        }
+        operations.push("Scan the history from the node".into());
+        if self.config.journal {
+            operations.push("Browse the transaction journal".into());
+        }
+        operations.push("Export the history to CSV".into());
         let selected_operation =
             inquire::Select::new("Select an operation", operations).prompt()?;
         let operation = match selected_operation.as_str() {
             "Transfer ERC20 Tokens" => self.transfer_erc20_tokens(),
             "Sign a message" => self.sign_message(),
+            "Sign typed data (EIP-712)" => self.sign_typed_data(),
+            "Verify a signature" => self.verify_signature(),
+            "Scan ERC20 allowances" => self.scan_erc20_allowances(),
+            "Call a contract function" => self.call_contract_function(),
+            "Call a view function" => self.call_view_function(),
+            "Deploy a contract" => self.deploy_contract(),
+            "Transfer ERC721 NFT" => self.transfer_erc721_token(),
+            "Transfer ERC1155 Tokens" => self.transfer_erc1155_tokens(),
+            "Transactions history" => self.explorer_history(),
+            "Scan the history from the node" => self.scan_history(),
+            "Browse the transaction journal" => self.browse_journal(),
+            "Export the history to CSV" => self.export_history(),
+            op if op.starts_with("Wrap or unwrap") => self.wrap_native_token(),
             _ => self.transfer_native_token(),
         }?;
         Ok(AppState {
@@ -219,16 +277,65 @@ impl AppState<WithAccount> {
     }
 
     fn sign_message(&self) -> Result<WithOperation> {
-        let message = inquire::Text::new("Message to sign").prompt()?;
+        let message = prompt_message("Message to sign")?;
         Ok(WithOperation::SignMessage(SignMessageOp {
             term: self.term.clone(),
-            message: message.into(),
+            message,
+            address: self.inner.address,
+            network: self.inner.network.clone(),
+            crypto_key_path: self.inner.crypto_key_path.clone(),
+        }))
+    }
+
+    fn sign_typed_data(&self) -> Result<WithOperation> {
+        let payload = prompt_json("EIP-712 typed data")?;
+        let typed_data = crate::eip712::parse(&payload)?;
+        Ok(WithOperation::SignTypedData(SignTypedDataOp {
+            term: self.term.clone(),
+            payload: payload.into_bytes(),
+            typed_data,
             address: self.inner.address,
             network: self.inner.network.clone(),
             crypto_key_path: self.inner.crypto_key_path.clone(),
         }))
     }
 
+    fn verify_signature(&self) -> Result<WithOperation> {
+        let personal_message = "Personal message (EIP-191)";
+        let typed_data = "Typed data (EIP-712)";
+        let kind = inquire::Select::new(
+            "What was signed?",
+            vec![personal_message, typed_data],
+        )
+        .prompt()?;
+        let hash = if kind == personal_message {
+            let message = prompt_message("Signed message")?;
+            crate::message::hash(&message)
+        } else {
+            let payload = prompt_json("Signed typed data")?;
+            crate::eip712::hash(&crate::eip712::parse(&payload)?)?
+        };
+        let signature = inquire::Text::new("Signature")
+            .with_validator(SignatureValidator)
+            .prompt()?
+            .trim()
+            .parse::<types::Signature>()?;
+        let expected = inquire::Text::new("Expected signer address")
+            .with_validator(self.address_validator())
+            .with_autocomplete(AddressBookAutoComplete::new(
+                self.config.contacts.clone(),
+            ))
+            .prompt()
+            .and_then(try_parse_address)?;
+        Ok(WithOperation::VerifySignature(VerifySignatureOp {
+            term: self.term.clone(),
+            hash,
+            signature,
+            expected,
+            contacts: self.config.contacts.clone(),
+        }))
+    }
+
     fn transfer_native_token(&self) -> Result<WithOperation> {
         let amount = inquire::Text::new("Amount to transfer")
             .with_validator(EtherAmountValidator)
@@ -237,27 +344,24 @@ impl AppState<WithAccount> {
                 ethers::utils::parse_ether(s)
                     .map_err(|e| inquire::InquireError::Custom(e.into()))
             })?;
-        let recipient = inquire::Text::new("Recipient address")
-            .with_validator(AddressValidator)
-            .with_autocomplete(AddressBookAutoComplete::new(
-                self.config.contacts.clone(),
-            ))
-            .prompt()
-            .and_then(try_parse_address)?;
+        let (recipient, ens_name) = self.prompt_recipient()?;
         Ok(WithOperation::NativeTransfer(NativeTransferOp {
             term: self.term.clone(),
             crypto_key_path: self.inner.crypto_key_path.clone(),
             to: recipient,
+            ens_name,
             amount,
             from: self.inner.address,
+            network_name: self.inner.network_name.clone(),
             network: self.inner.network.clone(),
             client: self.create_ethers_client()?,
+            contacts: self.config.contacts.clone(),
         }))
     }
 
     fn transfer_erc20_tokens(&self) -> Result<WithOperation> {
         let token = inquire::Text::new("Token address")
-            .with_validator(AddressValidator)
+            .with_validator(self.address_validator())
             .with_autocomplete(Erc20AutoComplete::new(
                 self.inner.network.erc20_tokens.clone(),
             ))
@@ -266,50 +370,498 @@ impl AppState<WithAccount> {
         let amount = inquire::Text::new("Amount to transfer")
             .with_validator(EtherAmountValidator)
             .prompt()?;
-        let recipient = inquire::Text::new("Recipient address")
-            .with_validator(AddressValidator)
-            .with_autocomplete(AddressBookAutoComplete::new(
-                self.config.contacts.clone(),
-            ))
-            .prompt()
-            .and_then(try_parse_address)?;
+        let (recipient, ens_name) = self.prompt_recipient()?;
         Ok(WithOperation::Erc20Transfer(Erc20TransferOp {
             term: self.term.clone(),
             crypto_key_path: self.inner.crypto_key_path.clone(),
             erc20_token: token,
             to: recipient,
+            ens_name,
             from: self.inner.address,
+            network_name: self.inner.network_name.clone(),
             network: self.inner.network.clone(),
             amount,
             client: self.create_ethers_client()?,
+            contacts: self.config.contacts.clone(),
         }))
     }
 
-    fn create_ethers_client(&self) -> Result<EthersClient> {
-        let reqwest_client = if let Some(ref proxy) = self.config.proxy {
-            let proxy = reqwest::Proxy::all(proxy)?;
-            reqwest::ClientBuilder::new()
-                .proxy(proxy)
-                .https_only(true)
-                .build()?
-        } else if self
-            .inner
-            .network
-            .rpc_url
-            .host_str()
-            .map(|s| s == "localhost")
-            .unwrap_or(false)
+    fn transfer_erc721_token(&self) -> Result<WithOperation> {
+        let contract = inquire::Text::new("NFT contract address")
+            .with_validator(self.address_validator())
+            .prompt()
+            .and_then(try_parse_address)?;
+        let token_id = inquire::Text::new("Token ID")
+            .with_validator(U256Validator)
+            .prompt()
+            .and_then(try_parse_u256)?;
+        let (recipient, ens_name) = self.prompt_recipient()?;
+        Ok(WithOperation::Erc721Transfer(Erc721TransferOp {
+            term: self.term.clone(),
+            crypto_key_path: self.inner.crypto_key_path.clone(),
+            contract,
+            token_id,
+            to: recipient,
+            ens_name,
+            from: self.inner.address,
+            network_name: self.inner.network_name.clone(),
+            network: self.inner.network.clone(),
+            client: self.create_ethers_client()?,
+            contacts: self.config.contacts.clone(),
+        }))
+    }
+
+    fn transfer_erc1155_tokens(&self) -> Result<WithOperation> {
+        let contract = inquire::Text::new("Token contract address")
+            .with_validator(self.address_validator())
+            .prompt()
+            .and_then(try_parse_address)?;
+        let mut ids = Vec::new();
+        let mut amounts = Vec::new();
+        loop {
+            let id = inquire::Text::new("Token ID")
+                .with_validator(U256Validator)
+                .prompt()
+                .and_then(try_parse_u256)?;
+            let amount = inquire::Text::new("Amount to transfer")
+                .with_validator(U256Validator)
+                .with_default("1")
+                .prompt()
+                .and_then(try_parse_u256)?;
+            ids.push(id);
+            amounts.push(amount);
+            let more = inquire::Confirm::new("Add another token ID?")
+                .with_default(false)
+                .prompt()?;
+            if !more {
+                break;
+            }
+        }
+        let (recipient, ens_name) = self.prompt_recipient()?;
+        Ok(WithOperation::Erc1155Transfer(Erc1155TransferOp {
+            term: self.term.clone(),
+            crypto_key_path: self.inner.crypto_key_path.clone(),
+            contract,
+            ids,
+            amounts,
+            to: recipient,
+            ens_name,
+            from: self.inner.address,
+            network_name: self.inner.network_name.clone(),
+            network: self.inner.network.clone(),
+            client: self.create_ethers_client()?,
+            contacts: self.config.contacts.clone(),
+        }))
+    }
+
+    fn wrap_native_token(&self) -> Result<WithOperation> {
+        let wrapped_token =
+            self.inner.network.wrapped_native_token.ok_or_else(|| {
+                eyre::eyre!("No wrapped native token configured")
+            })?;
+        let symbol = &self.inner.network.currency_symbol;
+        let wrap = format!("Wrap {symbol}");
+        let unwrap = format!("Unwrap W{symbol}");
+        let direction = inquire::Select::new(
+            "Select an action",
+            vec![wrap.clone(), unwrap],
+        )
+        .prompt()?;
+        let amount = inquire::Text::new("Amount")
+            .with_validator(EtherAmountValidator)
+            .prompt()
+            .and_then(|s| {
+                ethers::utils::parse_ether(s)
+                    .map_err(|e| inquire::InquireError::Custom(e.into()))
+            })?;
+        Ok(WithOperation::WrapNative(WrapNativeOp {
+            term: self.term.clone(),
+            crypto_key_path: self.inner.crypto_key_path.clone(),
+            wrapped_token,
+            wrap: direction == wrap,
+            amount,
+            from: self.inner.address,
+            network_name: self.inner.network_name.clone(),
+            network: self.inner.network.clone(),
+            client: self.create_ethers_client()?,
+        }))
+    }
+
+    fn scan_erc20_allowances(&self) -> Result<WithOperation> {
+        let from_block = inquire::Text::new("Scan from block")
+            .with_help_message(&format!(
+                "Empty to scan the last {} blocks",
+                crate::logscan::ERC20_SCAN_BLOCKS
+            ))
+            .with_validator(|s: &str| match s.trim() {
+                "" => Ok(validator::Validation::Valid),
+                s => validator::StringValidator::validate(
+                    &BlockNumberValidator,
+                    s,
+                ),
+            })
+            .prompt()?;
+        let from_block = match from_block.trim() {
+            "" => None,
+            s => Some(try_parse_block_number(s.to_string())?),
+        };
+        let to_block = inquire::Text::new("Scan to block")
+            .with_default("latest")
+            .with_validator(BlockNumberValidator)
+            .prompt()
+            .and_then(try_parse_block_number)?;
+        Ok(WithOperation::AllowanceScan(AllowanceScanOp {
+            term: self.term.clone(),
+            crypto_key_path: self.inner.crypto_key_path.clone(),
+            owner: self.inner.address,
+            from_block,
+            to_block,
+            network_name: self.inner.network_name.clone(),
+            network: self.inner.network.clone(),
+            client: self.create_ethers_client()?,
+        }))
+    }
+
+    fn call_contract_function(&self) -> Result<WithOperation> {
+        let (contract, abi) = self.select_contract()?;
+        let function = crate::abi::select_function(
+            "Select a function to call",
+            &abi,
+            crate::abi::is_mutable,
+        )?;
+        let args = crate::abi::prompt_arguments(
+            &function.inputs,
+            &self.config.contacts,
+            self.config.require_checksum,
+        )?;
+        let value = if function.state_mutability
+            == ethers::abi::StateMutability::Payable
         {
-            reqwest::ClientBuilder::new().build()?
+            inquire::Text::new("Amount to send")
+                .with_validator(EtherAmountValidator)
+                .with_default("0")
+                .prompt()
+                .and_then(|s| {
+                    ethers::utils::parse_ether(s)
+                        .map_err(|e| inquire::InquireError::Custom(e.into()))
+                })?
+        } else {
+            types::U256::zero()
+        };
+        Ok(WithOperation::ContractCall(ContractCallOp {
+            term: self.term.clone(),
+            crypto_key_path: self.inner.crypto_key_path.clone(),
+            contract,
+            function,
+            args,
+            value,
+            from: self.inner.address,
+            network_name: self.inner.network_name.clone(),
+            network: self.inner.network.clone(),
+            client: self.create_ethers_client()?,
+        }))
+    }
+
+    fn call_view_function(&self) -> Result<WithOperation> {
+        let (contract, abi) = self.select_contract()?;
+        let function = crate::abi::select_function(
+            "Select a function to call",
+            &abi,
+            |f| !crate::abi::is_mutable(f),
+        )?;
+        let args = crate::abi::prompt_arguments(
+            &function.inputs,
+            &self.config.contacts,
+            self.config.require_checksum,
+        )?;
+        let block = inquire::Text::new("At block")
+            .with_default("latest")
+            .with_validator(BlockNumberValidator)
+            .prompt()
+            .and_then(try_parse_block_number)?;
+        Ok(WithOperation::ViewCall(ViewCallOp {
+            term: self.term.clone(),
+            contract,
+            function,
+            args,
+            block,
+            client: self.create_ethers_client()?,
+        }))
+    }
+
+    fn deploy_contract(&self) -> Result<WithOperation> {
+        let path = inquire::Text::new("Contract artifact JSON file")
+            .with_help_message("e.g. Foundry's out/Contract.sol/Contract.json")
+            .prompt()?;
+        let crate::abi::Artifact {
+            abi,
+            bytecode,
+            payable_constructor,
+        } = crate::abi::load_artifact(path.trim())?;
+        let (code, args) = match abi.constructor() {
+            Some(constructor) => {
+                let args = crate::abi::prompt_arguments(
+                    &constructor.inputs,
+                    &self.config.contacts,
+                    self.config.require_checksum,
+                )?;
+                let code =
+                    constructor.encode_input(bytecode.to_vec(), &args)?;
+                let args = constructor
+                    .inputs
+                    .iter()
+                    .map(|p| p.name.clone())
+                    .zip(args)
+                    .collect();
+                (code.into(), args)
+            }
+            None => (bytecode, Vec::new()),
+        };
+        let value = if payable_constructor {
+            inquire::Text::new("Amount to send")
+                .with_validator(EtherAmountValidator)
+                .with_default("0")
+                .prompt()
+                .and_then(|s| {
+                    ethers::utils::parse_ether(s)
+                        .map_err(|e| inquire::InquireError::Custom(e.into()))
+                })?
+        } else {
+            types::U256::zero()
+        };
+        Ok(WithOperation::DeployContract(DeployContractOp {
+            term: self.term.clone(),
+            crypto_key_path: self.inner.crypto_key_path.clone(),
+            code,
+            args,
+            value,
+            from: self.inner.address,
+            network_name: self.inner.network_name.clone(),
+            network: self.inner.network.clone(),
+            client: self.create_ethers_client()?,
+        }))
+    }
+
+    fn browse_journal(&self) -> Result<WithOperation> {
+        let (_, journal) = open_journal()?;
+        let query = inquire::Text::new("Search the journal")
+            .with_help_message(
+                "A hash, an address, a token or a network, empty for all",
+            )
+            .prompt()?;
+        Ok(WithOperation::BrowseJournal(BrowseJournalOp {
+            term: self.term.clone(),
+            journal,
+            query,
+        }))
+    }
+
+    fn explorer_history(&self) -> Result<WithOperation> {
+        let network = &self.inner.network;
+        let api_url = network.explorer_api_url.clone().ok_or_else(|| {
+            eyre::eyre!("The network has no explorer API configured")
+        })?;
+        let kinds = vec![
+            format!("{} transactions", network.currency_symbol),
+            "ERC20 token transfers".into(),
+        ];
+        let selected_kind =
+            inquire::Select::new("Select the history", kinds).prompt()?;
+        let kind = if selected_kind == "ERC20 token transfers" {
+            crate::explorer::HistoryKind::TokenTransfers
         } else {
-            reqwest::ClientBuilder::new().https_only(true).build()?
+            crate::explorer::HistoryKind::Transactions
         };
-        let http_provider = ethers::providers::Http::new_with_client(
-            self.inner.network.rpc_url.clone(),
-            reqwest_client,
+        let client = create_http_client(&self.config, &api_url)?;
+        Ok(WithOperation::ExplorerHistory(ExplorerHistoryOp {
+            term: self.term.clone(),
+            explorer: crate::explorer::Explorer::new(
+                client,
+                api_url,
+                network.explorer_api_key.clone(),
+                network.chain_id,
+            ),
+            kind,
+            address: self.inner.address,
+            network: network.clone(),
+            contacts: self.config.contacts.clone(),
+        }))
+    }
+
+    fn scan_history(&self) -> Result<WithOperation> {
+        let passphrase = prompt_passphrase(
+            "History cache",
+            crate::logscan::HistoryCache::exists(),
+        )?;
+        let cache = crate::logscan::HistoryCache::load(&passphrase)?;
+        let help_message = format!(
+            "Empty to only scan the new blocks, or the last {} blocks for the \
+             first scan. The {} transfers are fetched block by block",
+            crate::logscan::ERC20_SCAN_BLOCKS,
+            self.inner.network.currency_symbol
         );
-        let ethers_client = EthersClient::new(http_provider);
-        Ok(ethers_client)
+        let from_block = inquire::Text::new("Scan back to block")
+            .with_help_message(&help_message)
+            .with_validator(|s: &str| match s.trim() {
+                "" => Ok(validator::Validation::Valid),
+                s => match s.parse::<u64>() {
+                    Ok(_) => Ok(validator::Validation::Valid),
+                    Err(e) => Ok(validator::Validation::Invalid(
+                        validator::ErrorMessage::Custom(e.to_string()),
+                    )),
+                },
+            })
+            .prompt()?;
+        let from_block = match from_block.trim() {
+            "" => None,
+            s => Some(s.parse::<u64>()?),
+        };
+        Ok(WithOperation::ScanHistory(ScanHistoryOp {
+            term: self.term.clone(),
+            address: self.inner.address,
+            from_block,
+            network: self.inner.network.clone(),
+            client: self.create_ethers_client()?,
+            contacts: self.config.contacts.clone(),
+            passphrase,
+            cache,
+        }))
+    }
+
+    fn export_history(&self) -> Result<WithOperation> {
+        let network = &self.inner.network;
+        let mut sources = Vec::new();
+        if self.config.journal || crate::journal::Journal::exists() {
+            sources.push("Transaction journal");
+        }
+        if network.explorer_api_url.is_some() {
+            sources.push("Explorer API");
+        }
+        if crate::logscan::HistoryCache::exists() {
+            sources.push("Node history cache");
+        }
+        if sources.is_empty() {
+            eyre::bail!(
+                "No history available, scan the history from the node first"
+            );
+        }
+        let selected_source =
+            inquire::Select::new("Export the history from", sources)
+                .prompt()?;
+        let source = match selected_source {
+            "Transaction journal" => {
+                let (_, journal) = open_journal()?;
+                HistorySource::Journal(journal)
+            }
+            "Explorer API" => {
+                let api_url =
+                    network.explorer_api_url.clone().ok_or_else(|| {
+                        eyre::eyre!(
+                            "The network has no explorer API configured"
+                        )
+                    })?;
+                let client = create_http_client(&self.config, &api_url)?;
+                HistorySource::Explorer(crate::explorer::Explorer::new(
+                    client,
+                    api_url,
+                    network.explorer_api_key.clone(),
+                    network.chain_id,
+                ))
+            }
+            _ => {
+                let passphrase = prompt_passphrase("History cache", true)?;
+                let cache = crate::logscan::HistoryCache::load(&passphrase)?;
+                HistorySource::NodeCache(cache)
+            }
+        };
+        let path = inquire::Text::new("Export to file")
+            .with_default("history.csv")
+            .prompt()?;
+        if std::path::Path::new(&path).exists() {
+            let overwrite = inquire::Confirm::new(&format!(
+                "{} already exists, do you want to overwrite it?",
+                path
+            ))
+            .with_default(false)
+            .prompt()?;
+            if !overwrite {
+                eyre::bail!("Aborted by user");
+            }
+        }
+        Ok(WithOperation::ExportHistory(ExportHistoryOp {
+            term: self.term.clone(),
+            source,
+            path,
+            address: self.inner.address,
+            network_name: self.inner.network_name.clone(),
+            network: network.clone(),
+            contacts: self.config.contacts.clone(),
+        }))
+    }
+
+    /// Asks the user for a contract address and loads its ABI, either from
+    /// the network config or from a JSON file.
+    fn select_contract(&self) -> Result<(types::Address, ethers::abi::Abi)> {
+        let contracts = &self.inner.network.contracts;
+        let address = inquire::Text::new("Contract address")
+            .with_validator(self.address_validator())
+            .with_autocomplete(ContractAutoComplete::new(contracts.clone()))
+            .prompt()
+            .and_then(try_parse_address)?;
+        let abi = match contracts.iter().find(|c| c.address == address) {
+            Some(contract) => contract.abi.clone(),
+            None => {
+                let path = inquire::Text::new("Contract ABI JSON file")
+                    .with_help_message("Plain ABI or a compiled artifact")
+                    .prompt()?;
+                crate::abi::load_from_file(path.trim())?
+            }
+        };
+        Ok((address, abi))
+    }
+
+    /// Asks for the recipient address, which could be a contact or an ENS
+    /// name if the network has an ENS registry.
+    fn prompt_recipient(&self) -> Result<(types::Address, Option<String>)> {
+        let ens_enabled = self.inner.network.ens_registry.is_some();
+        let help_message = if ens_enabled {
+            "An address, a contact or an ENS name"
+        } else {
+            "An address or a contact"
+        };
+        let recipient = inquire::Text::new("Recipient address")
+            .with_validator(RecipientValidator {
+                ens_enabled,
+                address: self.address_validator(),
+            })
+            .with_autocomplete(AddressBookAutoComplete::new(
+                self.config.contacts.clone(),
+            ))
+            .with_help_message(help_message)
+            .prompt()?;
+        if ens_enabled && crate::ens::is_name(&recipient) {
+            let client = self.create_ethers_client()?;
+            let name = recipient.trim().to_string();
+            let address = block_on(crate::ens::resolve(&client, &name))?;
+            self.term.write_line(&format!(
+                "Resolved {} to {}",
+                name,
+                ethers::utils::to_checksum(&address, None)
+            ))?;
+            Ok((address, Some(name)))
+        } else {
+            Ok((try_parse_address(recipient)?, None))
+        }
+    }
+
+    fn address_validator(&self) -> AddressValidator {
+        AddressValidator {
+            require_checksum: self.config.require_checksum,
+        }
+    }
+
+    fn create_ethers_client(&self) -> Result<EthersClient> {
+        create_ethers_client(&self.config, &self.inner.network)
     }
 }
 
@@ -322,14 +874,36 @@ pub struct SignMessageOp {
     crypto_key_path: CryptoKeyPath,
 }
 
+pub struct SignTypedDataOp {
+    term: console::Term,
+    /// The raw JSON payload, as sent to the signer.
+    payload: Vec<u8>,
+    typed_data: ethers::types::transaction::eip712::TypedData,
+    address: types::Address,
+    network: crate::config::Network,
+    crypto_key_path: CryptoKeyPath,
+}
+
+pub struct VerifySignatureOp {
+    term: console::Term,
+    /// The hash that was signed, EIP-191 or EIP-712.
+    hash: types::H256,
+    signature: types::Signature,
+    expected: types::Address,
+    contacts: Vec<crate::config::Contact>,
+}
+
 pub struct NativeTransferOp {
     term: console::Term,
     crypto_key_path: CryptoKeyPath,
     to: types::Address,
+    ens_name: Option<String>,
     from: types::Address,
     amount: ethers::types::U256,
+    network_name: String,
     network: crate::config::Network,
     client: EthersClient,
+    contacts: Vec<crate::config::Contact>,
 }
 
 pub struct Erc20TransferOp {
@@ -337,8 +911,101 @@ pub struct Erc20TransferOp {
     crypto_key_path: CryptoKeyPath,
     erc20_token: types::Address,
     to: types::Address,
+    ens_name: Option<String>,
     from: types::Address,
     amount: String,
+    network_name: String,
+    network: crate::config::Network,
+    client: EthersClient,
+    contacts: Vec<crate::config::Contact>,
+}
+
+pub struct Erc721TransferOp {
+    term: console::Term,
+    crypto_key_path: CryptoKeyPath,
+    contract: types::Address,
+    token_id: types::U256,
+    to: types::Address,
+    ens_name: Option<String>,
+    from: types::Address,
+    network_name: String,
+    network: crate::config::Network,
+    client: EthersClient,
+    contacts: Vec<crate::config::Contact>,
+}
+
+pub struct Erc1155TransferOp {
+    term: console::Term,
+    crypto_key_path: CryptoKeyPath,
+    contract: types::Address,
+    ids: Vec<types::U256>,
+    amounts: Vec<types::U256>,
+    to: types::Address,
+    ens_name: Option<String>,
+    from: types::Address,
+    network_name: String,
+    network: crate::config::Network,
+    client: EthersClient,
+    contacts: Vec<crate::config::Contact>,
+}
+
+pub struct WrapNativeOp {
+    term: console::Term,
+    crypto_key_path: CryptoKeyPath,
+    wrapped_token: types::Address,
+    /// `true` to wrap, `false` to unwrap.
+    wrap: bool,
+    amount: types::U256,
+    from: types::Address,
+    network_name: String,
+    network: crate::config::Network,
+    client: EthersClient,
+}
+
+pub struct AllowanceScanOp {
+    term: console::Term,
+    crypto_key_path: CryptoKeyPath,
+    owner: types::Address,
+    /// `None` to scan the last `logscan::ERC20_SCAN_BLOCKS` blocks.
+    from_block: Option<types::BlockNumber>,
+    to_block: types::BlockNumber,
+    network_name: String,
+    network: crate::config::Network,
+    client: EthersClient,
+}
+
+pub struct ContractCallOp {
+    term: console::Term,
+    crypto_key_path: CryptoKeyPath,
+    contract: types::Address,
+    function: ethers::abi::Function,
+    args: Vec<ethers::abi::Token>,
+    value: types::U256,
+    from: types::Address,
+    network_name: String,
+    network: crate::config::Network,
+    client: EthersClient,
+}
+
+pub struct ViewCallOp {
+    term: console::Term,
+    contract: types::Address,
+    function: ethers::abi::Function,
+    args: Vec<ethers::abi::Token>,
+    block: types::BlockNumber,
+    client: EthersClient,
+}
+
+pub struct DeployContractOp {
+    term: console::Term,
+    crypto_key_path: CryptoKeyPath,
+    /// The contract bytecode, with the constructor arguments appended.
+    code: types::Bytes,
+    /// Constructor arguments, for display only.
+    args: Vec<(String, ethers::abi::Token)>,
+    value: types::U256,
+    from: types::Address,
+    network_name: String,
     network: crate::config::Network,
     client: EthersClient,
 }
@@ -350,22 +1017,134 @@ struct SignRequest<'a> {
     data_type: ethereum::eth_sign_request::DataType,
 }
 
-pub enum WithOperation {
-    SignMessage(SignMessageOp),
-    NativeTransfer(NativeTransferOp),
-    Erc20Transfer(Erc20TransferOp),
-}
+struct SendRequest<'a> {
+    term: &'a console::Term,
+    client: &'a EthersClient,
+    network_name: &'a str,
+    network: &'a crate::config::Network,
+    from: types::Address,
+    crypto_key_path: &'a CryptoKeyPath,
+    tx: TypedTransaction,
+}
+
+struct RecipientCheck<'a> {
+    term: &'a console::Term,
+    client: &'a EthersClient,
+    network: &'a crate::config::Network,
+    contacts: &'a [crate::config::Contact],
+    from: types::Address,
+    to: types::Address,
+    ens_name: Option<&'a str>,
+    asset: crate::safety::Asset,
+}
+
+pub struct ExplorerHistoryOp {
+    term: console::Term,
+    explorer: crate::explorer::Explorer,
+    kind: crate::explorer::HistoryKind,
+    address: types::Address,
+    network: crate::config::Network,
+    contacts: Vec<crate::config::Contact>,
+}
+
+pub struct ScanHistoryOp {
+    term: console::Term,
+    address: types::Address,
+    /// The older block to scan back to, if any.
+    from_block: Option<u64>,
+    network: crate::config::Network,
+    client: EthersClient,
+    contacts: Vec<crate::config::Contact>,
+    passphrase: String,
+    cache: crate::logscan::HistoryCache,
+}
+
+/// Where the exported history comes from.
+pub enum HistorySource {
+    Journal(crate::journal::Journal),
+    Explorer(crate::explorer::Explorer),
+    NodeCache(crate::logscan::HistoryCache),
+}
+
+pub struct ExportHistoryOp {
+    term: console::Term,
+    source: HistorySource,
+    path: String,
+    address: types::Address,
+    network_name: String,
+    network: crate::config::Network,
+    contacts: Vec<crate::config::Contact>,
+}
+
+/// The spending of a transaction allowed by the policy, recorded in the
+/// ledger once the transaction is sent.
+struct PolicySpending {
+    network_name: String,
+    spending: crate::policy::Spending,
+    /// The ledger and its passphrase, only opened for the daily limits.
+    ledger: Option<(String, crate::policy::Ledger)>,
+}
+
+pub struct BrowseJournalOp {
+    term: console::Term,
+    journal: crate::journal::Journal,
+    query: String,
+}
+
+pub enum WithOperation {
+    SignMessage(SignMessageOp),
+    SignTypedData(SignTypedDataOp),
+    VerifySignature(VerifySignatureOp),
+    NativeTransfer(NativeTransferOp),
+    Erc20Transfer(Erc20TransferOp),
+    Erc721Transfer(Erc721TransferOp),
+    Erc1155Transfer(Erc1155TransferOp),
+    WrapNative(WrapNativeOp),
+    AllowanceScan(AllowanceScanOp),
+    ContractCall(ContractCallOp),
+    ViewCall(ViewCallOp),
+    DeployContract(DeployContractOp),
+    ExplorerHistory(ExplorerHistoryOp),
+    ScanHistory(ScanHistoryOp),
+    BrowseJournal(BrowseJournalOp),
+    ExportHistory(ExportHistoryOp),
+}
 
 impl AppState<WithOperation> {
     pub async fn execute(self) -> Result<Self> {
         match &self.inner {
             WithOperation::SignMessage(op) => self.sign_message(op)?,
+            WithOperation::SignTypedData(op) => self.sign_typed_data(op)?,
+            WithOperation::VerifySignature(op) => self.verify_signature(op)?,
             WithOperation::NativeTransfer(op) => {
                 self.transfer_native_token(op).await?
             }
             WithOperation::Erc20Transfer(op) => {
                 self.transfer_erc20_tokens(op).await?
             }
+            WithOperation::Erc721Transfer(op) => {
+                self.transfer_erc721_token(op).await?
+            }
+            WithOperation::Erc1155Transfer(op) => {
+                self.transfer_erc1155_tokens(op).await?
+            }
+            WithOperation::WrapNative(op) => self.wrap_native_token(op).await?,
+            WithOperation::AllowanceScan(op) => {
+                self.scan_erc20_allowances(op).await?
+            }
+            WithOperation::ContractCall(op) => {
+                self.call_contract_function(op).await?
+            }
+            WithOperation::ViewCall(op) => self.call_view_function(op).await?,
+            WithOperation::DeployContract(op) => {
+                self.deploy_contract(op).await?
+            }
+            WithOperation::ExplorerHistory(op) => {
+                self.explorer_history(op).await?
+            }
+            WithOperation::ScanHistory(op) => self.scan_history(op).await?,
+            WithOperation::BrowseJournal(op) => self.browse_journal(op)?,
+            WithOperation::ExportHistory(op) => self.export_history(op).await?,
         };
         Ok(self)
     }
@@ -380,6 +1159,13 @@ impl AppState<WithOperation> {
             ..
         }: &SignMessageOp,
     ) -> Result<()> {
+        term.write_line(&format!(
+            "Message: {}",
+            crate::message::display(message)
+        ))?;
+        // so it could be compared with the hash shown on the signer.
+        let hash = crate::message::hash(message);
+        term.write_line(&format!("EIP-191 Hash: {:?}", hash))?;
         let signature = self.sign_and_get_signature(SignRequest {
             message,
             address: *address,
@@ -387,6 +1173,98 @@ impl AppState<WithOperation> {
             data_type: ethereum::eth_sign_request::DataType::PersonalMessage,
         })?;
         term.write_line(&format!("Signature: 0x{}", signature))?;
+        let signed_message =
+            crate::message::SignedMessage::new(*address, message, &signature);
+        term.write_line(&serde_json::to_string_pretty(&signed_message)?)?;
+        Ok(())
+    }
+
+    fn sign_typed_data(
+        &self,
+        SignTypedDataOp {
+            term,
+            payload,
+            typed_data,
+            address,
+            network,
+            crypto_key_path,
+        }: &SignTypedDataOp,
+    ) -> Result<()> {
+        term.write_line("Domain:")?;
+        for line in crate::eip712::domain_lines(typed_data) {
+            term.write_line(&line)?;
+        }
+        term.write_line(&format!("Primary Type: {}", typed_data.primary_type))?;
+        term.write_line("Message:")?;
+        for line in crate::eip712::field_tree(typed_data) {
+            term.write_line(&line)?;
+        }
+        match typed_data.domain.chain_id {
+            Some(chain_id) if chain_id != network.chain_id => {
+                term.write_line(&format!(
+                    "WARNING: The domain chain id {} does not match the network chain id {}",
+                    chain_id, network.chain_id
+                ))?;
+            }
+            _ => {}
+        }
+        let hash = crate::eip712::hash(typed_data)?;
+        term.write_line(&format!("Hash: {:?}", hash))?;
+        let signature = self.sign_and_get_signature(SignRequest {
+            message: payload,
+            address: *address,
+            crypto_key_path,
+            data_type: ethereum::eth_sign_request::DataType::TypedData,
+        })?;
+        term.write_line(&format!("Signature: 0x{}", signature))?;
+        let signer = signature.recover(types::RecoveryMessage::Hash(hash))?;
+        if signer != *address {
+            eyre::bail!(
+                "Signature verification failed, expected {} but it was signed by {}",
+                ethers::utils::to_checksum(address, None),
+                ethers::utils::to_checksum(&signer, None)
+            );
+        }
+        term.write_line(&format!(
+            "Signature verified, signed by {}",
+            ethers::utils::to_checksum(&signer, None)
+        ))?;
+        Ok(())
+    }
+
+    fn verify_signature(
+        &self,
+        VerifySignatureOp {
+            term,
+            hash,
+            signature,
+            expected,
+            contacts,
+        }: &VerifySignatureOp,
+    ) -> Result<()> {
+        term.write_line(&format!("Hash: {:?}", hash))?;
+        let signer = signature
+            .recover(types::RecoveryMessage::Hash(*hash))
+            .map_err(|e| eyre::eyre!("Failed to recover the signer: {}", e))?;
+        let label = |address: &types::Address| {
+            contacts
+                .iter()
+                .find(|c| c.address == *address)
+                .map(ToString::to_string)
+                .unwrap_or_else(|| ethers::utils::to_checksum(address, None))
+        };
+        if signer == *expected {
+            term.write_line(&format!(
+                "✔ Signature matches, signed by {}",
+                label(&signer)
+            ))?;
+        } else {
+            term.write_line(&format!(
+                "✘ Signature mismatch, signed by {} but expected {}",
+                label(&signer),
+                label(expected)
+            ))?;
+        }
         Ok(())
     }
 
@@ -399,107 +1277,52 @@ impl AppState<WithOperation> {
             amount,
             from,
             client,
+            network_name,
             network,
+            contacts,
+            ens_name,
         }: &NativeTransferOp,
     ) -> Result<()> {
-        let chain_id = client.get_chainid().await?;
         term.write_line("Fetching Balance...")?;
         let balance = client
             .get_balance(*from, None)
             .await
             .map_err(|e| eyre::eyre!("Failed to fetch balance: {}", e))?;
         term.write_line(&format!(
-            "Balance: {}",
-            ethers::utils::format_ether(balance)
+            "Balance of {}: {}{}",
+            self.label(client, network, *from).await,
+            ethers::utils::format_ether(balance),
+            crate::price::usd_suffix(client, network, None, balance, 18).await
         ))?;
         term.write_line(&format!(
-            "Sending {} to {}",
+            "Sending {}{} to {}",
             ethers::utils::format_ether(*amount),
-            to
+            crate::price::usd_suffix(client, network, None, *amount, 18).await,
+            self.label(client, network, *to).await
         ))?;
-        // fetch the nonce
-        let nonce = client
-            .get_transaction_count(*from, None)
-            .await
-            .map_err(|e| eyre::eyre!("Failed to fetch nonce: {}", e))?;
+        self.check_recipient(RecipientCheck {
+            term,
+            client,
+            network,
+            contacts,
+            from: *from,
+            to: *to,
+            ens_name: ens_name.as_deref(),
+            asset: crate::safety::Asset::Native,
+        })
+        .await?;
         let mut tx = TypedTransaction::default();
-        let tx = tx
-            .set_to(*to)
-            .set_value(*amount)
-            .set_nonce(nonce)
-            .set_chain_id(chain_id.as_u64());
-        // calcculate the gas limit
-        let gas_limit = client
-            .estimate_gas(tx, None)
-            .await
-            .map_err(|e| eyre::eyre!("Failed to estimate gas: {}", e))?;
-        // print the gas price
-        let gas_price = client
-            .get_gas_price()
-            .await
-            .map_err(|e| eyre::eyre!("Failed to fetch gas price: {}", e))?;
-        term.write_line(&format!(
-            "Gas Price: {} Gwei",
-            ethers::utils::format_ether(gas_price),
-        ))?;
-        term.write_line(&format!("Gas Limit: {}", gas_limit))?;
-        let tx = tx.set_gas(gas_limit).set_gas_price(gas_price);
-        let tx_hash = tx.sighash();
-        term.write_line(&format!(
-            "Transaction: {}",
-            serde_json::to_string_pretty(&tx)?
-        ))?;
-        // ask for confirmation
-        let ok = inquire::Confirm::new("Do you want to send this transaction?")
-            .prompt()?;
-        if !ok {
-            eyre::bail!("Aborted by user");
-        }
-        let signature = self.sign_and_get_signature(SignRequest {
-            message: tx.rlp().as_ref(),
-            address: *from,
+        tx.set_to(*to).set_value(*amount);
+        self.send_transaction(SendRequest {
+            term,
+            client,
+            network_name,
+            network,
+            from: *from,
             crypto_key_path,
-            data_type: ethereum::eth_sign_request::DataType::TypedTransaction,
-        })?;
-        term.write_line(&format!("Signature: 0x{}", signature))?;
-        let tx_signed = tx.rlp_signed(&signature);
-        let pending_tx = client.send_raw_transaction(tx_signed).await?;
-        if let Some(explorer) = network.explorer_url.as_ref() {
-            let url = format!("{}/tx/{:?}", explorer, tx_hash);
-            term.write_line(&format!("View transaction at {}", url))?;
-        }
-        let progress_spinner = ProgressBar::new_spinner();
-        progress_spinner
-            .enable_steady_tick(core::time::Duration::from_millis(50));
-        progress_spinner.set_style(
-            ProgressStyle::default_spinner()
-                .tick_strings(&["☱", "☲", "☴"])
-                .template("{spinner:.green} {msg}")?,
-        );
-        progress_spinner.set_message(format!(
-            "Transaction {} sent, waiting for confirmation...",
-            tx_hash
-        ));
-        let maybe_receipt = pending_tx.confirmations(1).await?;
-        match maybe_receipt {
-            Some(receipt) => {
-                progress_spinner.finish_with_message(format!(
-                    "Transaction {} confirmed!",
-                    receipt.transaction_hash,
-                ));
-                term.write_line(&format!(
-                    "Receipt: {}",
-                    serde_json::to_string_pretty(&receipt)?
-                ))?;
-            }
-            None => {
-                progress_spinner.finish_with_message(format!(
-                    "Transaction {} failed!",
-                    tx_hash,
-                ));
-                eyre::bail!("Transaction failed");
-            }
-        }
+            tx,
+        })
+        .await?;
         Ok(())
     }
 
@@ -513,11 +1336,13 @@ impl AppState<WithOperation> {
             from,
             erc20_token,
             client,
+            network_name,
             network,
+            contacts,
+            ens_name,
         }: &Erc20TransferOp,
     ) -> Result<()> {
         let client = Arc::new(client.clone());
-        let chain_id = client.get_chainid().await?;
         let contract = crate::erc20::Erc20::new(*erc20_token, client);
         // Check user balance
         let balance = contract
@@ -536,79 +1361,1119 @@ impl AppState<WithOperation> {
             .await
             .map_err(|e| eyre::eyre!("Failed to fetch symbol: {}", e))?;
         let formated = ethers::utils::format_units(balance, decimals as u32)?;
-        term.write_line(&format!("Balance: {} {}", formated, token_symbol))?;
+        let token = Some(*erc20_token);
+        term.write_line(&format!(
+            "Balance of {}: {} {}{}",
+            self.label(contract.client().as_ref(), network, *from).await,
+            formated,
+            token_symbol,
+            crate::price::usd_suffix(
+                contract.client().as_ref(),
+                network,
+                token,
+                balance,
+                decimals
+            )
+            .await
+        ))?;
         let parsed_amount =
             ethers::utils::parse_units(amount.to_string(), decimals as u32)?;
         term.write_line(&format!(
-            "Sending {} {} to {}",
+            "Sending {} {}{} to {}",
             ethers::utils::format_units(parsed_amount, decimals as u32)?,
             token_symbol,
-            to
+            crate::price::usd_suffix(
+                contract.client().as_ref(),
+                network,
+                token,
+                parsed_amount.into(),
+                decimals
+            )
+            .await,
+            self.label(contract.client().as_ref(), network, *to).await
+        ))?;
+        self.check_recipient(RecipientCheck {
+            term,
+            client: contract.client().as_ref(),
+            network,
+            contacts,
+            from: *from,
+            to: *to,
+            ens_name: ens_name.as_deref(),
+            asset: crate::safety::Asset::Token(*erc20_token),
+        })
+        .await?;
+        let transfer_tx = contract.transfer(*to, parsed_amount.into());
+        let transfer_tx = transfer_tx.from(*from);
+        self.send_transaction(SendRequest {
+            term,
+            client: contract.client().as_ref(),
+            network_name,
+            network,
+            from: *from,
+            crypto_key_path,
+            tx: transfer_tx.tx,
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn transfer_erc721_token(
+        &self,
+        Erc721TransferOp {
+            term,
+            crypto_key_path,
+            contract,
+            token_id,
+            to,
+            from,
+            network_name,
+            network,
+            client,
+            contacts,
+            ens_name,
+        }: &Erc721TransferOp,
+    ) -> Result<()> {
+        let client = Arc::new(client.clone());
+        let contract = crate::erc721::Erc721::new(*contract, client.clone());
+        // name and symbol are optional in the standard.
+        let name = contract.name().call().await.unwrap_or_default();
+        let symbol = contract.symbol().call().await.unwrap_or_default();
+        let owner =
+            contract.owner_of(*token_id).call().await.map_err(|e| {
+                eyre::eyre!("Failed to fetch token owner: {}", e)
+            })?;
+        if owner != *from {
+            eyre::bail!(
+                "Token #{} is owned by {}, not by {}",
+                token_id,
+                ethers::utils::to_checksum(&owner, None),
+                ethers::utils::to_checksum(from, None)
+            );
+        }
+        term.write_line(&format!(
+            "Sending {} [{}] #{} to {}",
+            name,
+            symbol,
+            token_id,
+            self.label(&client, network, *to).await
+        ))?;
+        self.check_recipient(RecipientCheck {
+            term,
+            client: &client,
+            network,
+            contacts,
+            from: *from,
+            to: *to,
+            ens_name: ens_name.as_deref(),
+            asset: crate::safety::Asset::Token(contract.address()),
+        })
+        .await?;
+        let transfer_tx = contract
+            .safe_transfer_from(*from, *to, *token_id)
+            .from(*from);
+        // `safeTransferFrom` reverts if the recipient is a contract that
+        // does not implement `onERC721Received`, so we dry call it first.
+        let code = client.get_code(*to, None).await.map_err(|e| {
+            eyre::eyre!("Failed to fetch recipient code: {}", e)
+        })?;
+        if !code.is_empty() {
+            term.write_line(
+                "Recipient is a contract, checking if it can receive the token...",
+            )?;
+            transfer_tx.call().await.map_err(|e| {
+                eyre::eyre!(
+                    "Recipient contract can not receive ERC721 tokens: {}",
+                    e
+                )
+            })?;
+        }
+        self.send_transaction(SendRequest {
+            term,
+            client: &client,
+            network_name,
+            network,
+            from: *from,
+            crypto_key_path,
+            tx: transfer_tx.tx,
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn transfer_erc1155_tokens(
+        &self,
+        Erc1155TransferOp {
+            term,
+            crypto_key_path,
+            contract,
+            ids,
+            amounts,
+            to,
+            from,
+            network_name,
+            network,
+            client,
+            contacts,
+            ens_name,
+        }: &Erc1155TransferOp,
+    ) -> Result<()> {
+        let client = Arc::new(client.clone());
+        let contract = crate::erc1155::Erc1155::new(*contract, client.clone());
+        // the same id could be sent more than once in a batch.
+        let mut totals =
+            std::collections::BTreeMap::<types::U256, types::U256>::new();
+        for (id, amount) in ids.iter().zip(amounts) {
+            let total = totals.entry(*id).or_default();
+            *total = total.saturating_add(*amount);
+        }
+        let unique_ids = totals.keys().copied().collect::<Vec<_>>();
+        let balances = contract
+            .balance_of_batch(vec![*from; unique_ids.len()], unique_ids)
+            .call()
+            .await
+            .map_err(|e| eyre::eyre!("Failed to fetch balances: {}", e))?;
+        for ((id, amount), balance) in totals.iter().zip(&balances) {
+            term.write_line(&format!(
+                "Token #{}: Balance: {}, Sending: {}",
+                id, balance, amount
+            ))?;
+            if amount > balance {
+                eyre::bail!("Insufficient balance of token #{}", id);
+            }
+        }
+        term.write_line(&format!(
+            "Sending to {}",
+            self.label(&client, network, *to).await
+        ))?;
+        self.check_recipient(RecipientCheck {
+            term,
+            client: &client,
+            network,
+            contacts,
+            from: *from,
+            to: *to,
+            ens_name: ens_name.as_deref(),
+            asset: crate::safety::Asset::Token(contract.address()),
+        })
+        .await?;
+        let transfer_tx = if let ([id], [amount]) = (&ids[..], &amounts[..]) {
+            contract.safe_transfer_from(
+                *from,
+                *to,
+                *id,
+                *amount,
+                Default::default(),
+            )
+        } else {
+            contract.safe_batch_transfer_from(
+                *from,
+                *to,
+                ids.clone(),
+                amounts.clone(),
+                Default::default(),
+            )
+        };
+        let transfer_tx = transfer_tx.from(*from);
+        // the safe transfers revert if the recipient is a contract that does
+        // not implement the `IERC1155Receiver`, so we dry call it first.
+        let code = client.get_code(*to, None).await.map_err(|e| {
+            eyre::eyre!("Failed to fetch recipient code: {}", e)
+        })?;
+        if !code.is_empty() {
+            term.write_line(
+                "Recipient is a contract, checking if it can receive the tokens...",
+            )?;
+            transfer_tx.call().await.map_err(|e| {
+                eyre::eyre!(
+                    "Recipient contract can not receive ERC1155 tokens: {}",
+                    e
+                )
+            })?;
+        }
+        self.send_transaction(SendRequest {
+            term,
+            client: &client,
+            network_name,
+            network,
+            from: *from,
+            crypto_key_path,
+            tx: transfer_tx.tx,
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn wrap_native_token(
+        &self,
+        WrapNativeOp {
+            term,
+            crypto_key_path,
+            wrapped_token,
+            wrap,
+            amount,
+            from,
+            network_name,
+            network,
+            client,
+        }: &WrapNativeOp,
+    ) -> Result<()> {
+        let client = Arc::new(client.clone());
+        let contract = crate::weth::Weth::new(*wrapped_token, client.clone());
+        let symbol = &network.currency_symbol;
+        let wrapped_symbol = contract
+            .symbol()
+            .call()
+            .await
+            .unwrap_or_else(|_| format!("W{symbol}"));
+        let balance = client
+            .get_balance(*from, None)
+            .await
+            .map_err(|e| eyre::eyre!("Failed to fetch balance: {}", e))?;
+        let wrapped_balance = contract
+            .balance_of(*from)
+            .call()
+            .await
+            .map_err(|e| eyre::eyre!("Failed to fetch balance: {}", e))?;
+        term.write_line(&format!(
+            "Balance: {} {}, {} {}",
+            ethers::utils::format_ether(balance),
+            symbol,
+            ethers::utils::format_ether(wrapped_balance),
+            wrapped_symbol
         ))?;
-        let nonce = contract
-            .client()
-            .get_transaction_count(*from, None)
+        // the wrapped token has the price of the native currency.
+        let usd =
+            crate::price::usd_suffix(&client, network, None, *amount, 18).await;
+        let tx = if *wrap {
+            if *amount > balance {
+                eyre::bail!("Insufficient {} balance", symbol);
+            }
+            term.write_line(&format!(
+                "Wrapping {} {}{} into {}",
+                ethers::utils::format_ether(*amount),
+                symbol,
+                usd,
+                wrapped_symbol
+            ))?;
+            contract.deposit().value(*amount).from(*from).tx
+        } else {
+            if *amount > wrapped_balance {
+                eyre::bail!("Insufficient {} balance", wrapped_symbol);
+            }
+            term.write_line(&format!(
+                "Unwrapping {} {}{} into {}",
+                ethers::utils::format_ether(*amount),
+                wrapped_symbol,
+                usd,
+                symbol
+            ))?;
+            contract.withdraw(*amount).from(*from).tx
+        };
+        self.send_transaction(SendRequest {
+            term,
+            client: &client,
+            network_name,
+            network,
+            from: *from,
+            crypto_key_path,
+            tx,
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn scan_erc20_allowances(
+        &self,
+        AllowanceScanOp {
+            term,
+            crypto_key_path,
+            owner,
+            from_block,
+            to_block,
+            network_name,
+            network,
+            client,
+        }: &AllowanceScanOp,
+    ) -> Result<()> {
+        if network.erc20_tokens.is_empty() {
+            eyre::bail!("No ERC20 tokens configured for this network");
+        }
+        let to_block = block_number(client, *to_block).await?;
+        let from_block = match from_block {
+            Some(block) => block_number(client, *block).await?,
+            None => to_block.saturating_sub(crate::logscan::ERC20_SCAN_BLOCKS),
+        };
+        term.write_line(&format!(
+            "Scanning the blocks {} to {}",
+            from_block, to_block
+        ))?;
+        let chunks = crate::logscan::chunks(from_block, to_block);
+        let client = Arc::new(client.clone());
+        // (token, spender, allowance, decimals)
+        let mut allowances = Vec::new();
+        for token in &network.erc20_tokens {
+            term.write_line(&format!(
+                "Scanning {} approvals...",
+                token.symbol
+            ))?;
+            let contract =
+                crate::erc20::Erc20::new(token.address, client.clone());
+            // the same spender could be approved many times, we only care
+            // about the current allowance.
+            let mut spenders = std::collections::BTreeSet::new();
+            let progress = ProgressBar::new(chunks.len() as u64);
+            for (from, to) in &chunks {
+                let approvals = contract
+                    .approval_filter()
+                    .topic1(*owner)
+                    .from_block(*from)
+                    .to_block(*to)
+                    .query()
+                    .await
+                    .map_err(|e| {
+                        eyre::eyre!("Failed to fetch approvals: {}", e)
+                    })?;
+                spenders.extend(approvals.into_iter().map(|a| a.spender));
+                progress.inc(1);
+            }
+            progress.finish_and_clear();
+            if spenders.is_empty() {
+                continue;
+            }
+            let decimals =
+                contract.decimals().call().await.map_err(|e| {
+                    eyre::eyre!("Failed to fetch decimals: {}", e)
+                })?;
+            for spender in spenders {
+                let allowance =
+                    contract.allowance(*owner, spender).call().await.map_err(
+                        |e| eyre::eyre!("Failed to fetch allowance: {}", e),
+                    )?;
+                if !allowance.is_zero() {
+                    allowances.push((token, spender, allowance, decimals));
+                }
+            }
+        }
+        if allowances.is_empty() {
+            term.write_line("No active allowances found")?;
+            return Ok(());
+        }
+        let allowances_display = allowances
+            .iter()
+            .map(|(token, spender, allowance, decimals)| {
+                let amount = if *allowance == types::U256::MAX {
+                    String::from("Unlimited")
+                } else {
+                    ethers::utils::format_units(*allowance, *decimals as u32)
+                        .unwrap_or_else(|_| allowance.to_string())
+                };
+                format!(
+                    "{} {} to {}",
+                    amount,
+                    token.symbol,
+                    ethers::utils::to_checksum(spender, None)
+                )
+            })
+            .collect::<Vec<_>>();
+        for allowance in &allowances_display {
+            term.write_line(allowance)?;
+        }
+        // selected by index, two allowances could be displayed the same.
+        let selected = inquire::MultiSelect::new(
+            "Select allowances to revoke",
+            allowances_display,
+        )
+        .with_help_message("Cancel to keep all of them")
+        .raw_prompt_skippable()?
+        .unwrap_or_default();
+        for selected_allowance in selected {
+            let (token, spender, ..) = allowances[selected_allowance.index];
+            term.write_line(&format!(
+                "Revoking {} allowance of {}",
+                token.symbol,
+                ethers::utils::to_checksum(&spender, None)
+            ))?;
+            let contract =
+                crate::erc20::Erc20::new(token.address, client.clone());
+            let revoke_tx =
+                contract.approve(spender, types::U256::zero()).from(*owner);
+            self.send_transaction(SendRequest {
+                term,
+                client: &client,
+                network_name,
+                network,
+                from: *owner,
+                crypto_key_path,
+                tx: revoke_tx.tx,
+            })
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn call_contract_function(
+        &self,
+        ContractCallOp {
+            term,
+            crypto_key_path,
+            contract,
+            function,
+            args,
+            value,
+            from,
+            network_name,
+            network,
+            client,
+        }: &ContractCallOp,
+    ) -> Result<()> {
+        let calldata = function.encode_input(args)?;
+        term.write_line(&format!(
+            "Calling {} on {}",
+            crate::abi::display_function(function),
+            ethers::utils::to_checksum(contract, None)
+        ))?;
+        for (param, arg) in function.inputs.iter().zip(args) {
+            term.write_line(&format!(
+                "  {}: {}",
+                param.name,
+                crate::abi::display_token(arg)
+            ))?;
+        }
+        if !value.is_zero() {
+            term.write_line(&format!(
+                "Sending {} {}{}",
+                ethers::utils::format_ether(*value),
+                network.currency_symbol,
+                crate::price::usd_suffix(client, network, None, *value, 18)
+                    .await
+            ))?;
+        }
+        let mut tx = TypedTransaction::default();
+        tx.set_to(*contract)
+            .set_value(*value)
+            .set_data(calldata.into());
+        self.send_transaction(SendRequest {
+            term,
+            client,
+            network_name,
+            network,
+            from: *from,
+            crypto_key_path,
+            tx,
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn call_view_function(
+        &self,
+        ViewCallOp {
+            term,
+            contract,
+            function,
+            args,
+            block,
+            client,
+        }: &ViewCallOp,
+    ) -> Result<()> {
+        let calldata = function.encode_input(args)?;
+        // we don't set the `from` field, so we don't leak our address.
+        let mut tx = TypedTransaction::default();
+        tx.set_to(*contract).set_data(calldata.into());
+        let output = client
+            .call(&tx, Some((*block).into()))
+            .await
+            .map_err(|e| eyre::eyre!("Failed to call the contract: {}", e))?;
+        let tokens = function.decode_output(&output)?;
+        term.write_line(&format!(
+            "{} at block {}:",
+            crate::abi::display_function(function),
+            block
+        ))?;
+        for (i, (param, token)) in
+            function.outputs.iter().zip(&tokens).enumerate()
+        {
+            let name = if param.name.is_empty() {
+                i.to_string()
+            } else {
+                param.name.clone()
+            };
+            term.write_line(&format!(
+                "  {} ({}): {}",
+                name,
+                param.kind,
+                crate::abi::display_token(token)
+            ))?;
+        }
+        Ok(())
+    }
+
+    async fn deploy_contract(
+        &self,
+        DeployContractOp {
+            term,
+            crypto_key_path,
+            code,
+            args,
+            value,
+            from,
+            network_name,
+            network,
+            client,
+        }: &DeployContractOp,
+    ) -> Result<()> {
+        term.write_line(&format!(
+            "Deploying a contract ({} bytes)",
+            code.len()
+        ))?;
+        for (name, arg) in args {
+            term.write_line(&format!(
+                "  {}: {}",
+                name,
+                crate::abi::display_token(arg)
+            ))?;
+        }
+        if !value.is_zero() {
+            term.write_line(&format!(
+                "Sending {} {}{}",
+                ethers::utils::format_ether(*value),
+                network.currency_symbol,
+                crate::price::usd_suffix(client, network, None, *value, 18)
+                    .await
+            ))?;
+        }
+        // no `to` address, it is a contract creation transaction.
+        let mut tx = TypedTransaction::default();
+        tx.set_data(code.clone()).set_value(*value);
+        let receipt = self
+            .send_transaction(SendRequest {
+                term,
+                client,
+                network_name,
+                network,
+                from: *from,
+                crypto_key_path,
+                tx,
+            })
+            .await?;
+        match receipt.contract_address {
+            Some(address) => {
+                term.write_line(&format!(
+                    "Contract deployed at {}",
+                    ethers::utils::to_checksum(&address, None)
+                ))?;
+                if let Some(explorer) = network.explorer_url.as_ref() {
+                    term.write_line(&format!(
+                        "View contract at {}/address/{:?}",
+                        explorer, address
+                    ))?;
+                }
+            }
+            None => eyre::bail!("No contract address found in the receipt"),
+        }
+        Ok(())
+    }
+
+    /// The address with its reverse ENS name, if enabled for the network.
+    async fn label(
+        &self,
+        client: &EthersClient,
+        network: &crate::config::Network,
+        address: types::Address,
+    ) -> String {
+        let name = if ens_labels_enabled(&self.config, network) {
+            crate::ens::lookup(client, address).await
+        } else {
+            None
+        };
+        crate::ens::label(address, name.as_deref())
+    }
+
+    async fn explorer_history(
+        &self,
+        ExplorerHistoryOp {
+            term,
+            explorer,
+            kind,
+            address,
+            network,
+            contacts,
+        }: &ExplorerHistoryOp,
+    ) -> Result<()> {
+        let mut page = 1;
+        loop {
+            term.write_line(&format!("Fetching page {page}..."))?;
+            let entries = explorer
+                .history(*kind, *address, &network.currency_symbol, page)
+                .await?;
+            if entries.is_empty() && page == 1 {
+                term.write_line("No transactions found")?;
+                return Ok(());
+            }
+            for line in crate::history::table(&entries, contacts) {
+                term.write_line(&line)?;
+            }
+            let mut actions = Vec::new();
+            if entries.len() == crate::explorer::PAGE_SIZE {
+                actions.push("Next page");
+            }
+            if page > 1 {
+                actions.push("Previous page");
+            }
+            actions.push("Done");
+            match inquire::Select::new("Select an action", actions).prompt()? {
+                "Next page" => page += 1,
+                "Previous page" => page -= 1,
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    async fn scan_history(
+        &self,
+        ScanHistoryOp {
+            term,
+            address,
+            from_block,
+            network,
+            client,
+            contacts,
+            passphrase,
+            cache,
+        }: &ScanHistoryOp,
+    ) -> Result<()> {
+        // the most recent blocks are left for the next scan, once they can
+        // no longer be reorganized.
+        let latest = client
+            .get_block_number()
+            .await
+            .map_err(|e| eyre::eyre!("Failed to fetch block number: {}", e))?
+            .as_u64()
+            .saturating_sub(crate::logscan::REORG_SAFETY_BLOCKS);
+        let mut cache = cache.clone();
+        let history = cache.account(network.chain_id, *address);
+        let result = scan_account_history(
+            term,
+            client,
+            &network.currency_symbol,
+            history,
+            latest,
+            *from_block,
+        )
+        .await;
+        // save what was scanned so far, the next scan resumes from there.
+        cache.save(passphrase)?;
+        result?;
+        let history = cache.account(network.chain_id, *address);
+        if history.entries.is_empty() {
+            term.write_line("No transactions found")?;
+        } else {
+            for line in crate::history::table(&history.entries, contacts) {
+                term.write_line(&line)?;
+            }
+        }
+        for line in history.scanned_blocks(&network.currency_symbol) {
+            term.write_line(&line)?;
+        }
+        term.write_line(
+            "The older transfers are not included, scan back to an older \
+             block to add them",
+        )?;
+        Ok(())
+    }
+
+    fn browse_journal(
+        &self,
+        BrowseJournalOp {
+            term,
+            journal,
+            query,
+        }: &BrowseJournalOp,
+    ) -> Result<()> {
+        let entries = journal.search(query);
+        if entries.is_empty() {
+            term.write_line("No transactions found")?;
+            return Ok(());
+        }
+        let entries_display =
+            entries.iter().map(ToString::to_string).collect::<Vec<_>>();
+        let selected_entry =
+            inquire::Select::new("Select a transaction", entries_display)
+                .raw_prompt()?;
+        let entry = entries[selected_entry.index];
+        term.write_line(&to_checksummed_json(entry)?)?;
+        let explorer = self
+            .config
+            .networks
+            .get(&entry.network)
+            .and_then(|n| n.explorer_url.as_ref());
+        if let Some(explorer) = explorer {
+            term.write_line(&format!(
+                "View transaction at {}/tx/{:?}",
+                explorer, entry.hash
+            ))?;
+        }
+        Ok(())
+    }
+
+    async fn export_history(
+        &self,
+        ExportHistoryOp {
+            term,
+            source,
+            path,
+            address,
+            network_name,
+            network,
+            contacts,
+        }: &ExportHistoryOp,
+    ) -> Result<()> {
+        let symbol = &network.currency_symbol;
+        let entries = match source {
+            HistorySource::Journal(journal) => journal
+                .entries
+                .iter()
+                .filter(|e| e.from == *address && e.network == *network_name)
+                .flat_map(|e| e.history(symbol))
+                .collect(),
+            HistorySource::Explorer(explorer) => {
+                use crate::explorer::HistoryKind;
+                term.write_line("Fetching the transactions...")?;
+                let mut entries = explorer
+                    .all(HistoryKind::Transactions, *address, symbol)
+                    .await?;
+                term.write_line("Fetching the token transfers...")?;
+                entries.extend(
+                    explorer
+                        .all(HistoryKind::TokenTransfers, *address, symbol)
+                        .await?,
+                );
+                entries
+            }
+            HistorySource::NodeCache(cache) => {
+                match cache.get(network.chain_id, *address) {
+                    Some(history) => {
+                        for line in history.scanned_blocks(symbol) {
+                            term.write_line(&line)?;
+                        }
+                        history.entries.clone()
+                    }
+                    None => Vec::new(),
+                }
+            }
+        };
+        if entries.is_empty() {
+            term.write_line("No transactions found")?;
+            return Ok(());
+        }
+        let file = std::fs::File::create(path)
+            .map_err(|e| eyre::eyre!("Failed to create {}: {}", path, e))?;
+        crate::history::write_csv(
+            std::io::BufWriter::new(file),
+            &entries,
+            network_name,
+            symbol,
+            contacts,
+        )?;
+        term.write_line(&format!(
+            "Exported {} transfers to {}",
+            entries.len(),
+            path
+        ))?;
+        Ok(())
+    }
+
+    /// Shows the recipient safety warnings, if any, and asks the user to
+    /// confirm sending to this recipient anyway.
+    ///
+    /// Possible address poisoning requires the user to type the end of the
+    /// recipient address, to make sure they actually looked at it.
+    async fn check_recipient(
+        &self,
+        RecipientCheck {
+            term,
+            client,
+            network,
+            contacts,
+            from,
+            to,
+            ens_name,
+            asset,
+        }: RecipientCheck<'_>,
+    ) -> Result<()> {
+        let mut warnings = crate::safety::check_recipient(
+            client, network, contacts, to, asset,
+        )
+        .await?;
+        let to_checksum = ethers::utils::to_checksum(&to, None);
+        if let Some(name) = ens_name {
+            term.write_line(&format!(
+                "ENS: {} resolves to {}",
+                name, to_checksum
+            ))?;
+            match crate::ens::lookup(client, to).await {
+                Some(primary) if primary == name => {
+                    term.write_line("ENS: Reverse record matches")?;
+                }
+                Some(primary) => warnings.push(format!(
+                    "The reverse record of {} is {}, not {}",
+                    to_checksum, primary, name
+                )),
+                None => {
+                    term.write_line("ENS: No reverse record is set")?;
+                }
+            }
+        } else if ens_labels_enabled(&self.config, network) {
+            if let Some(name) = crate::ens::lookup(client, to).await {
+                term.write_line(&format!("ENS: {} is {}", to_checksum, name))?;
+            }
+        }
+        for warning in &warnings {
+            term.write_line(&format!("WARNING: {}", warning))?;
+        }
+        let mut poisoning = Vec::new();
+        for contact in crate::safety::lookalike_contacts(to, contacts) {
+            poisoning.push(format!(
+                "Recipient {} looks like your contact {}, but it is a \
+                 different address",
+                to_checksum, contact
+            ));
+        }
+        match crate::safety::RecentTransfers::scan(client, from).await {
+            Ok(transfers) => {
+                if transfers.zero_value.contains(&to) {
+                    poisoning.push(format!(
+                        "Recipient {} was found in a recent zero-value \
+                         transfer, it is most likely an address poisoning \
+                         attack",
+                        to_checksum
+                    ));
+                }
+                for recipient in transfers.lookalike_recipients(to) {
+                    poisoning.push(format!(
+                        "Recipient {} looks like {}, which you recently sent \
+                         tokens to, but it is a different address",
+                        to_checksum,
+                        ethers::utils::to_checksum(&recipient, None)
+                    ));
+                }
+            }
+            Err(e) => {
+                term.write_line(&format!(
+                    "WARNING: Failed to scan for address poisoning: {}",
+                    e
+                ))?;
+            }
+        }
+        for warning in &poisoning {
+            term.write_line(&format!("DANGER: {}", warning))?;
+        }
+        if !poisoning.is_empty() {
+            let expected = &to_checksum[to_checksum.len() - 8..];
+            let typed = inquire::Text::new(
+                "Type the last 8 characters of the recipient to continue",
+            )
+            .prompt()?;
+            if !typed.trim().eq_ignore_ascii_case(expected) {
+                eyre::bail!("Aborted, recipient was not confirmed");
+            }
+        } else if !warnings.is_empty() {
+            let ok =
+                inquire::Confirm::new("Do you want to send to this recipient?")
+                    .with_default(false)
+                    .prompt()?;
+            if !ok {
+                eyre::bail!("Aborted by user");
+            }
+        }
+        Ok(())
+    }
+
+    /// Fills the missing transaction fields, shows it for review, signs it
+    /// using the QR signer and broadcasts it, waiting for the receipt.
+    async fn send_transaction(
+        &self,
+        SendRequest {
+            term,
+            client,
+            network_name,
+            network,
+            from,
+            crypto_key_path,
+            mut tx,
+        }: SendRequest<'_>,
+    ) -> Result<types::TransactionReceipt> {
+        let chain_id = client.get_chainid().await?;
+        // fetch the nonce
+        let nonce = client
+            .get_transaction_count(from, None)
             .await
             .map_err(|e| eyre::eyre!("Failed to fetch nonce: {}", e))?;
-        let transfer_tx = contract.transfer(*to, parsed_amount.into());
-        let mut transfer_tx = transfer_tx.from(*from);
-        match transfer_tx.estimate_gas().await {
-            Ok(gas_limit) => {
-                term.write_line(&format!("Gas Limit: {}", gas_limit))?;
-                transfer_tx = transfer_tx.gas(gas_limit);
+        tx.set_from(from)
+            .set_nonce(nonce)
+            .set_chain_id(chain_id.as_u64());
+        // decoded and simulated first, the gas estimation fails for the
+        // transactions that would revert.
+        let calldata = tx.data().map(|d| d.to_vec()).unwrap_or_default();
+        let to = tx.to_addr().copied();
+        let contract = to
+            .and_then(|to| network.contracts.iter().find(|c| c.address == to));
+        if let Some(call) = crate::decoder::decode(&calldata, contract) {
+            let token = match to {
+                Some(to) if call.is_token_call() => {
+                    crate::decoder::TokenInfo::fetch(client, to).await
+                }
+                _ => None,
+            };
+            let price = match (to, &token) {
+                (Some(to), Some(_)) => {
+                    crate::price::token_price(client, network, Some(to)).await
+                }
+                _ => None,
+            };
+            term.write_line("Decoded call:")?;
+            for line in call.lines(token.as_ref(), price.as_ref()) {
+                term.write_line(&format!("  {}", line))?;
+            }
+        }
+        match crate::simulation::simulate(client, &tx).await {
+            Ok(simulation) => {
+                if simulation.traced {
+                    term.write_line("Simulated balance changes:")?;
+                } else {
+                    term.write_line("Expected balance changes:")?;
+                }
+                let ens_labels = ens_labels_enabled(&self.config, network);
+                for line in
+                    simulation.describe(client, network, from, ens_labels).await
+                {
+                    term.write_line(&format!("  {}", line))?;
+                }
+                if let Some(reason) = &simulation.revert_reason {
+                    term.write_line(&format!(
+                        "WARNING: Simulation reverted, transaction will fail: {}",
+                        reason
+                    ))?;
+                }
             }
             Err(e) => {
                 term.write_line(&format!(
-                    "Failed to estimate gas, reason: {}",
+                    "WARNING: Failed to simulate the transaction: {}",
                     e
                 ))?;
             }
+        }
+        // calcculate the gas limit, the user could still send it with a
+        // manual gas limit if the estimation fails.
+        let gas_limit = match client.estimate_gas(&tx, None).await {
+            Ok(gas_limit) => gas_limit,
+            Err(e) => {
+                match crate::simulation::provider_revert_reason(&e) {
+                    Some(reason) => term.write_line(&format!(
+                        "WARNING: Failed to estimate gas, the transaction \
+                         would revert: {}",
+                        reason
+                    ))?,
+                    None => term.write_line(&format!(
+                        "WARNING: Failed to estimate gas, reason: {}",
+                        e
+                    ))?,
+                }
+                let send_anyway = inquire::Confirm::new(
+                    "Do you want to send it anyway, with a manual gas limit?",
+                )
+                .with_default(false)
+                .prompt()?;
+                if !send_anyway {
+                    eyre::bail!("Aborted by user");
+                }
+                inquire::Text::new("Gas limit")
+                    .with_validator(U256Validator)
+                    .prompt()
+                    .and_then(try_parse_u256)?
+            }
         };
-        let gas_price = contract
-            .client()
+        // print the gas price
+        let gas_price = client
             .get_gas_price()
             .await
             .map_err(|e| eyre::eyre!("Failed to fetch gas price: {}", e))?;
         term.write_line(&format!(
             "Gas Price: {} Gwei",
-            ethers::utils::format_ether(gas_price),
+            ethers::utils::format_units(gas_price, "gwei")?,
         ))?;
-        let mut transfer_tx = transfer_tx.gas_price(gas_price);
-        let tx_inner = transfer_tx
-            .tx
-            .set_chain_id(chain_id.as_u64())
-            .set_nonce(nonce)
-            .clone();
-        transfer_tx.tx = tx_inner;
+        term.write_line(&format!("Gas Limit: {}", gas_limit))?;
+        let max_fee = gas_limit.saturating_mul(gas_price);
+        term.write_line(&format!(
+            "Max Fee: {} {}{}",
+            crate::history::format_units(max_fee, 18),
+            network.currency_symbol,
+            crate::price::usd_suffix(client, network, None, max_fee, 18).await
+        ))?;
+        tx.set_gas(gas_limit).set_gas_price(gas_price);
         term.write_line(&format!(
             "Transaction: {}",
-            serde_json::to_string_pretty(&transfer_tx.tx)?
+            to_checksummed_json(&tx)?
         ))?;
-        let tx_hash = transfer_tx.tx.sighash();
-        // dry call.
-        let result = transfer_tx.call().await;
-        match result {
-            Ok(r) => {
-                term.write_line(&format!("Dry call result: {:?}", r))?;
-            }
-            Err(e) => {
-                term.write_line(
-                    "WARNING: Dry call failed. Transaction may fail.",
-                )?;
-                term.write_line(&format!("Error: {}", e))?;
-            }
+        // ask for confirmation
+        let ok = inquire::Confirm::new("Do you want to send this transaction?")
+            .prompt()?;
+        if !ok {
+            eyre::bail!("Aborted by user");
         }
-        let tx_rlp = transfer_tx.tx.rlp();
+        let policy_spending = self
+            .check_policy(client, network_name, network, &tx)
+            .await?;
+        // opened before signing, to catch a wrong passphrase early.
+        let mut journal = if self.config.journal {
+            Some(open_journal()?)
+        } else {
+            None
+        };
         let signature = self.sign_and_get_signature(SignRequest {
-            message: tx_rlp.as_ref(),
-            address: *from,
+            message: tx.rlp().as_ref(),
+            address: from,
             crypto_key_path,
             data_type: ethereum::eth_sign_request::DataType::TypedTransaction,
         })?;
         term.write_line(&format!("Signature: 0x{}", signature))?;
-        let tx_signed = transfer_tx.tx.rlp_signed(&signature);
-        let client = contract.client();
+        let tx_signed = tx.rlp_signed(&signature);
         let pending_tx = client.send_raw_transaction(tx_signed).await?;
+        if let Some(PolicySpending {
+            network_name,
+            spending,
+            ledger: Some((passphrase, mut ledger)),
+        }) = policy_spending
+        {
+            ledger.record(&network_name, &spending, crate::policy::now());
+            ledger.save(&passphrase)?;
+        }
+        let tx_hash = pending_tx.tx_hash();
+        let mut journal_entry = None;
+        if let Some((passphrase, journal)) = journal.as_mut() {
+            let spending =
+                crate::policy::Spending::from_tx(client, network, &tx).await;
+            let entry = crate::journal::JournalEntry {
+                timestamp: crate::policy::now(),
+                network: network_name.to_string(),
+                hash: tx_hash,
+                nonce: tx.nonce().copied().unwrap_or_default(),
+                from,
+                recipient: spending.recipient.or_else(|| tx.to_addr().copied()),
+                transfers: journal_transfers(client, &spending).await,
+                status: crate::journal::TransactionStatus::Pending,
+                fee: None,
+            };
+            journal.upsert(entry.clone());
+            if let Err(e) = journal.save(passphrase) {
+                term.write_line(&format!(
+                    "WARNING: Failed to update the journal: {}",
+                    e
+                ))?;
+            }
+            journal_entry = Some(entry);
+        }
         if let Some(explorer) = network.explorer_url.as_ref() {
             let url = format!("{}/tx/{:?}", explorer, tx_hash);
             term.write_line(&format!("View transaction at {}", url))?;
@@ -625,25 +2490,211 @@ impl AppState<WithOperation> {
             "Transaction {} sent, waiting for confirmation...",
             tx_hash
         ));
-        let maybe_receipt = pending_tx.confirmations(1).await?;
-        match maybe_receipt {
-            Some(receipt) => {
-                progress_spinner.finish_with_message(format!(
-                    "Transaction {} confirmed!",
-                    receipt.transaction_hash,
-                ));
+        let required = crate::confirmation::confirmations(network);
+        let outcome = crate::confirmation::wait(
+            client,
+            network,
+            tx_hash,
+            from,
+            nonce,
+            |progress| {
+                if let crate::confirmation::Progress::Confirming(n) = progress {
+                    progress_spinner.set_message(format!(
+                        "Transaction {} mined, {}/{} confirmations...",
+                        tx_hash, n, required
+                    ));
+                }
+            },
+        )
+        .await?;
+        let status = match &outcome {
+            crate::confirmation::Outcome::Mined(receipt)
+                if receipt.status == Some(1.into()) =>
+            {
+                crate::journal::TransactionStatus::Confirmed
+            }
+            crate::confirmation::Outcome::Mined(_) => {
+                crate::journal::TransactionStatus::Failed
+            }
+            crate::confirmation::Outcome::Replaced => {
+                crate::journal::TransactionStatus::Dropped
+            }
+            crate::confirmation::Outcome::TimedOut => {
+                crate::journal::TransactionStatus::Pending
+            }
+        };
+        if let (Some((passphrase, journal)), Some(mut entry)) =
+            (journal.as_mut(), journal_entry)
+        {
+            entry.status = status.clone();
+            if let crate::confirmation::Outcome::Mined(receipt) = &outcome {
+                entry.fee = receipt
+                    .gas_used
+                    .zip(receipt.effective_gas_price)
+                    .map(|(used, price)| used * price);
+            }
+            journal.upsert(entry);
+            if let Err(e) = journal.save(passphrase) {
                 term.write_line(&format!(
-                    "Receipt: {}",
-                    serde_json::to_string_pretty(&receipt)?
+                    "WARNING: Failed to update the journal: {}",
+                    e
                 ))?;
             }
-            None => {
+        }
+        let receipt = match outcome {
+            crate::confirmation::Outcome::Mined(receipt) => *receipt,
+            crate::confirmation::Outcome::Replaced => {
+                progress_spinner.finish_with_message(format!(
+                    "Transaction {} was replaced or dropped!",
+                    tx_hash,
+                ));
+                eyre::bail!(
+                    "Nonce {} was used by another transaction, {:?} will \
+                     never be mined",
+                    nonce,
+                    tx_hash
+                );
+            }
+            crate::confirmation::Outcome::TimedOut => {
                 progress_spinner.finish_with_message(format!(
-                    "Transaction {} failed!",
+                    "Transaction {} is still pending!",
                     tx_hash,
                 ));
-                eyre::bail!("Transaction failed");
+                eyre::bail!(
+                    "Timed out waiting for {} confirmations of {:?}, it could \
+                     still be mined later",
+                    required,
+                    tx_hash
+                );
+            }
+        };
+        if status == crate::journal::TransactionStatus::Confirmed {
+            progress_spinner.finish_with_message(format!(
+                "Transaction {} confirmed!",
+                receipt.transaction_hash,
+            ));
+        } else {
+            progress_spinner.finish_with_message(format!(
+                "Transaction {} reverted!",
+                receipt.transaction_hash,
+            ));
+        }
+        self.print_receipt(term, client, network, &receipt).await?;
+        if let Some(block) = receipt.block_number {
+            for line in crate::confirmation::finality(client, block).await {
+                term.write_line(&line)?;
+            }
+        }
+        if status != crate::journal::TransactionStatus::Confirmed {
+            eyre::bail!("Transaction {:?} failed", receipt.transaction_hash);
+        }
+        Ok(receipt)
+    }
+
+    /// Checks the transaction against the spending policy of the network, if
+    /// any, asking the user to type the amounts above the confirmation
+    /// threshold.
+    ///
+    /// Returns the spending to record once the transaction is sent.
+    async fn check_policy(
+        &self,
+        client: &EthersClient,
+        network_name: &str,
+        network: &crate::config::Network,
+        tx: &TypedTransaction,
+    ) -> Result<Option<PolicySpending>> {
+        let policy = match self.config.policies.networks.get(network_name) {
+            Some(policy) => policy,
+            None => return Ok(None),
+        };
+        let spending =
+            crate::policy::Spending::from_tx(client, network, tx).await;
+        // the ledger is only needed, and opened, for the daily limits.
+        let ledger = if crate::policy::has_daily_limit(policy) {
+            let passphrase = prompt_passphrase(
+                "Spending ledger",
+                crate::policy::Ledger::exists(),
+            )?;
+            let ledger = crate::policy::Ledger::load(&passphrase)?;
+            Some((passphrase, ledger))
+        } else {
+            None
+        };
+        let phrases = crate::policy::check(
+            policy,
+            network_name,
+            &self.config.contacts,
+            &spending,
+            ledger
+                .as_ref()
+                .map(|(_, ledger)| ledger)
+                .unwrap_or(&Default::default()),
+            crate::policy::now(),
+        )?;
+        for phrase in phrases {
+            let typed = inquire::Text::new(&format!(
+                "Type \"{}\" to confirm this amount",
+                phrase
+            ))
+            .prompt()?;
+            if typed.trim() != phrase {
+                eyre::bail!("Aborted, amount was not confirmed");
+            }
+        }
+        Ok(Some(PolicySpending {
+            network_name: network_name.to_string(),
+            spending,
+            ledger,
+        }))
+    }
+
+    /// Prints the receipt summary and its decoded token events, the raw
+    /// receipt is only printed in debug mode.
+    async fn print_receipt(
+        &self,
+        term: &console::Term,
+        client: &EthersClient,
+        network: &crate::config::Network,
+        receipt: &types::TransactionReceipt,
+    ) -> Result<()> {
+        for line in crate::receipt::summary(receipt, &network.currency_symbol) {
+            term.write_line(&line)?;
+        }
+        let events = crate::receipt::decode_events(&receipt.logs);
+        if !events.is_empty() {
+            let mut tokens = std::collections::HashMap::new();
+            let mut labels = std::collections::HashMap::new();
+            for event in &events {
+                let token = event.token();
+                if let std::collections::hash_map::Entry::Vacant(entry) =
+                    tokens.entry(token)
+                {
+                    let info =
+                        crate::decoder::TokenInfo::fetch(client, token).await;
+                    entry.insert(info);
+                }
+                for address in event.addresses() {
+                    if let std::collections::hash_map::Entry::Vacant(entry) =
+                        labels.entry(address)
+                    {
+                        entry
+                            .insert(self.label(client, network, address).await);
+                    }
+                }
             }
+            term.write_line("Events:")?;
+            for event in &events {
+                let info = tokens.get(&event.token()).and_then(Option::as_ref);
+                let line =
+                    event.describe(info, |address| labels[&address].clone());
+                term.write_line(&format!("  {}", line))?;
+            }
+        }
+        if self.config.debug {
+            term.write_line(&format!(
+                "Receipt: {}",
+                serde_json::to_string_pretty(receipt)?
+            ))?;
         }
         Ok(())
     }
@@ -728,14 +2779,189 @@ impl validator::StringValidator for EtherAmountValidator {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-struct AddressValidator;
+struct AddressValidator {
+    require_checksum: bool,
+}
 
 impl validator::StringValidator for AddressValidator {
     fn validate(
         &self,
         s: &str,
     ) -> Result<validator::Validation, inquire::CustomUserError> {
-        match try_parse_address(s.to_string()) {
+        let address = match try_parse_address(s.to_string()) {
+            Ok(address) => address,
+            Err(e) => {
+                return Ok(validator::Validation::Invalid(
+                    validator::ErrorMessage::Custom(e.to_string()),
+                ))
+            }
+        };
+        // the address of the "name | address" contacts is checked too.
+        let s = s.rsplit(" | ").next().unwrap_or(s).trim();
+        match check_address_checksum(s, address, self.require_checksum) {
+            Ok(()) => Ok(validator::Validation::Valid),
+            Err(e) => Ok(validator::Validation::Invalid(
+                validator::ErrorMessage::Custom(e),
+            )),
+        }
+    }
+}
+
+/// Validates the EIP-55 checksum of mixed case addresses, pointing out the
+/// characters that differ from the checksum by wrapping them in brackets.
+///
+/// Single case addresses have no checksum, they are rejected only if
+/// `require_checksum` is set.
+pub(crate) fn check_address_checksum(
+    s: &str,
+    address: types::Address,
+    require_checksum: bool,
+) -> std::result::Result<(), String> {
+    let hex = s.trim_start_matches("0x");
+    let has_upper = hex.chars().any(|c| c.is_ascii_uppercase());
+    let has_lower = hex.chars().any(|c| c.is_ascii_lowercase());
+    if !(has_upper && has_lower) {
+        if require_checksum && (has_upper || has_lower) {
+            return Err("A checksummed (EIP-55) address is required".into());
+        }
+        return Ok(());
+    }
+    let checksummed = ethers::utils::to_checksum(&address, None);
+    let expected = checksummed.trim_start_matches("0x");
+    if expected == hex {
+        return Ok(());
+    }
+    let marked = hex
+        .chars()
+        .zip(expected.chars())
+        .map(|(c, e)| {
+            if c == e {
+                c.to_string()
+            } else {
+                format!("[{c}]")
+            }
+        })
+        .collect::<String>();
+    Err(format!(
+        "Invalid EIP-55 checksum, check the marked characters: 0x{marked}"
+    ))
+}
+
+/// Pretty prints the value as JSON, with its addresses checksummed.
+fn to_checksummed_json(value: &impl serde::Serialize) -> Result<String> {
+    let mut value = serde_json::to_value(value)?;
+    checksum_addresses(&mut value);
+    Ok(serde_json::to_string_pretty(&value)?)
+}
+
+fn checksum_addresses(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::String(s)
+            if s.len() == 42 && s.starts_with("0x") =>
+        {
+            if let Ok(address) = s.parse::<types::Address>() {
+                *s = ethers::utils::to_checksum(&address, None);
+            }
+        }
+        serde_json::Value::Array(values) => {
+            values.iter_mut().for_each(checksum_addresses)
+        }
+        serde_json::Value::Object(fields) => {
+            // the calldata could be 20 bytes long.
+            fields
+                .iter_mut()
+                .filter(|(key, _)| !matches!(key.as_str(), "data" | "input"))
+                .for_each(|(_, value)| checksum_addresses(value))
+        }
+        _ => {}
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RecipientValidator {
+    ens_enabled: bool,
+    address: AddressValidator,
+}
+
+impl validator::StringValidator for RecipientValidator {
+    fn validate(
+        &self,
+        s: &str,
+    ) -> Result<validator::Validation, inquire::CustomUserError> {
+        if self.ens_enabled && crate::ens::is_name(s) {
+            return Ok(validator::Validation::Valid);
+        }
+        self.address.validate(s)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct HexValidator;
+
+impl validator::StringValidator for HexValidator {
+    fn validate(
+        &self,
+        s: &str,
+    ) -> Result<validator::Validation, inquire::CustomUserError> {
+        if !s.trim().starts_with("0x") {
+            return Ok(validator::Validation::Invalid(
+                validator::ErrorMessage::Custom(
+                    "Hex must start with 0x".into(),
+                ),
+            ));
+        }
+        match crate::message::parse_hex(s) {
+            Ok(_) => Ok(validator::Validation::Valid),
+            Err(e) => Ok(validator::Validation::Invalid(
+                validator::ErrorMessage::Custom(e.to_string()),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SignatureValidator;
+
+impl validator::StringValidator for SignatureValidator {
+    fn validate(
+        &self,
+        s: &str,
+    ) -> Result<validator::Validation, inquire::CustomUserError> {
+        match s.trim().parse::<types::Signature>() {
+            Ok(_) => Ok(validator::Validation::Valid),
+            Err(e) => Ok(validator::Validation::Invalid(
+                validator::ErrorMessage::Custom(e.to_string()),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct U256Validator;
+
+impl validator::StringValidator for U256Validator {
+    fn validate(
+        &self,
+        s: &str,
+    ) -> Result<validator::Validation, inquire::CustomUserError> {
+        match try_parse_u256(s.to_string()) {
+            Ok(_) => Ok(validator::Validation::Valid),
+            Err(e) => Ok(validator::Validation::Invalid(
+                validator::ErrorMessage::Custom(e.to_string()),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BlockNumberValidator;
+
+impl validator::StringValidator for BlockNumberValidator {
+    fn validate(
+        &self,
+        s: &str,
+    ) -> Result<validator::Validation, inquire::CustomUserError> {
+        match try_parse_block_number(s.to_string()) {
             Ok(_) => Ok(validator::Validation::Valid),
             Err(e) => Ok(validator::Validation::Invalid(
                 validator::ErrorMessage::Custom(e.to_string()),
@@ -745,12 +2971,12 @@ impl validator::StringValidator for AddressValidator {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-struct AddressBookAutoComplete {
+pub(crate) struct AddressBookAutoComplete {
     contacts: Vec<crate::config::Contact>,
 }
 
 impl AddressBookAutoComplete {
-    fn new(contacts: Vec<crate::config::Contact>) -> Self {
+    pub(crate) fn new(contacts: Vec<crate::config::Contact>) -> Self {
         Self { contacts }
     }
 }
@@ -773,7 +2999,13 @@ impl inquire::Autocomplete for AddressBookAutoComplete {
                         .to_lowercase()
                         .contains(input)
             })
-            .map(|contact| format!("{} | {:?}", contact.name, contact.address))
+            .map(|contact| {
+                format!(
+                    "{} | {}",
+                    contact.name,
+                    ethers::utils::to_checksum(&contact.address, None)
+                )
+            })
             .collect();
         Ok(suggestions)
     }
@@ -814,7 +3046,64 @@ impl inquire::Autocomplete for Erc20AutoComplete {
                 token.name.to_lowercase().contains(input)
                     || token.address.to_string().to_lowercase().contains(input)
             })
-            .map(|token| format!("{} | {:?}", token.name, token.address))
+            .map(|token| {
+                format!(
+                    "{} | {}",
+                    token.name,
+                    ethers::utils::to_checksum(&token.address, None)
+                )
+            })
+            .collect();
+        Ok(suggestions)
+    }
+
+    fn get_completion(
+        &mut self,
+        _input: &str,
+        _highlighted_suggestion: Option<String>,
+    ) -> Result<inquire::autocompletion::Replacement, inquire::CustomUserError>
+    {
+        // we don't want to replace the input with a suggestion
+        Ok(inquire::autocompletion::Replacement::None)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct ContractAutoComplete {
+    contracts: Vec<crate::config::ContractConfig>,
+}
+
+impl ContractAutoComplete {
+    fn new(contracts: Vec<crate::config::ContractConfig>) -> Self {
+        Self { contracts }
+    }
+}
+
+impl inquire::Autocomplete for ContractAutoComplete {
+    fn get_suggestions(
+        &mut self,
+        input: &str,
+    ) -> Result<Vec<String>, inquire::CustomUserError> {
+        // search for the input as a contract name or address
+        // and return the list of suggestions
+        let suggestions = self
+            .contracts
+            .iter()
+            .filter(|contract| {
+                contract.name.to_lowercase().contains(input)
+                    || contract
+                        .address
+                        .to_string()
+                        .to_lowercase()
+                        .contains(input)
+            })
+            .map(|contract| {
+                format!(
+                    "{} | {}",
+                    contract.name,
+                    ethers::utils::to_checksum(&contract.address, None)
+                )
+            })
             .collect();
         Ok(suggestions)
     }
@@ -830,6 +3119,312 @@ impl inquire::Autocomplete for Erc20AutoComplete {
     }
 }
 
+/// Asks the user for a message, as a single line of text, a multi-line text
+/// written in the editor, `0x` prefixed hex or loaded from a file.
+fn prompt_message(message: &str) -> Result<Vec<u8>> {
+    let text = "Text";
+    let multi_line = "Multi-line text (editor)";
+    let hex = "Hex (0x...)";
+    let file = "Load from a file";
+    let source =
+        inquire::Select::new(message, vec![text, multi_line, hex, file])
+            .prompt()?;
+    let bytes = if source == text {
+        inquire::Text::new(message).prompt()?.into_bytes()
+    } else if source == multi_line {
+        inquire::Editor::new(message).prompt()?.into_bytes()
+    } else if source == hex {
+        let hex = inquire::Text::new(message)
+            .with_validator(HexValidator)
+            .prompt()?;
+        crate::message::parse_hex(&hex)?
+    } else {
+        let path = inquire::Text::new("File path").prompt()?;
+        std::fs::read(path.trim())?
+    };
+    Ok(bytes)
+}
+
+fn create_ethers_client(
+    config: &crate::config::Config,
+    network: &crate::config::Network,
+) -> Result<EthersClient> {
+    let reqwest_client = create_http_client(config, &network.rpc_url)?;
+    let http_provider = ethers::providers::Http::new_with_client(
+        network.rpc_url.clone(),
+        reqwest_client,
+    );
+    let mut ethers_client = EthersClient::new(http_provider);
+    if let Some(registry) = network.ens_registry {
+        ethers_client = ethers_client.ens(registry);
+    }
+    Ok(ethers_client)
+}
+
+/// Creates the HTTP client used to reach the url, through the configured
+/// proxy if any.
+fn create_http_client(
+    config: &crate::config::Config,
+    url: &url::Url,
+) -> Result<reqwest::Client> {
+    let client = if let Some(ref proxy) = config.proxy {
+        let proxy = reqwest::Proxy::all(proxy)?;
+        reqwest::ClientBuilder::new()
+            .proxy(proxy)
+            .https_only(true)
+            .build()?
+    } else if url.host_str().map(|s| s == "localhost").unwrap_or(false) {
+        reqwest::ClientBuilder::new().build()?
+    } else {
+        reqwest::ClientBuilder::new().https_only(true).build()?
+    };
+    Ok(client)
+}
+
+/// Returns `true` if the reverse ENS names of the shown addresses should be
+/// looked up.
+fn ens_labels_enabled(
+    config: &crate::config::Config,
+    network: &crate::config::Network,
+) -> bool {
+    !config.privacy_strict && network.ens_registry.is_some()
+}
+
+/// Scans the new blocks, since the last scan, for the account transfers,
+/// then the older blocks down to `from_block`, if any.
+///
+/// The scanned blocks are updated after each scanned range, so an
+/// interrupted scan is resumed by the next one.
+async fn scan_account_history(
+    term: &console::Term,
+    client: &EthersClient,
+    currency_symbol: &str,
+    history: &mut crate::logscan::AccountHistory,
+    latest: u64,
+    from_block: Option<u64>,
+) -> Result<()> {
+    let start = history.erc20_checkpoint.map(|b| b + 1).unwrap_or_else(|| {
+        latest.saturating_sub(crate::logscan::ERC20_SCAN_BLOCKS)
+    });
+    let chunks = crate::logscan::chunks(start, latest);
+    term.write_line(&format!(
+        "Scanning the token transfers of blocks {start} to {latest}..."
+    ))?;
+    let progress = ProgressBar::new(chunks.len() as u64);
+    let mut tokens = std::collections::HashMap::new();
+    for range in chunks {
+        let entries = crate::logscan::scan_erc20(
+            client,
+            history.account,
+            range,
+            &mut tokens,
+        )
+        .await?;
+        history.merge(entries);
+        history.erc20_checkpoint = Some(range.1);
+        history.erc20_start.get_or_insert(range.0);
+        progress.inc(1);
+    }
+    progress.finish_and_clear();
+    if let (Some(from), Some(start)) = (from_block, history.erc20_start) {
+        if from < start {
+            term.write_line(&format!(
+                "Scanning the token transfers of blocks {from} to {}...",
+                start - 1
+            ))?;
+            // backwards, so the first scanned block is moved after each range.
+            let chunks = crate::logscan::chunks(from, start - 1);
+            let progress = ProgressBar::new(chunks.len() as u64);
+            for range in chunks.into_iter().rev() {
+                let entries = crate::logscan::scan_erc20(
+                    client,
+                    history.account,
+                    range,
+                    &mut tokens,
+                )
+                .await?;
+                history.merge(entries);
+                history.erc20_start = Some(range.0);
+                progress.inc(1);
+            }
+            progress.finish_and_clear();
+        }
+    }
+    let start = latest
+        .saturating_sub(crate::logscan::NATIVE_SCAN_BLOCKS)
+        .max(history.native_checkpoint.map(|b| b + 1).unwrap_or_default());
+    if let Some(checkpoint) = history.native_checkpoint {
+        if checkpoint + 1 < start {
+            term.write_line(&format!(
+                "WARNING: the {currency_symbol} transfers of blocks {} to {} \
+                 are not scanned, too many blocks since the last scan",
+                checkpoint + 1,
+                start - 1
+            ))?;
+            history.native_start = None;
+        }
+    }
+    term.write_line(&format!(
+        "Scanning the {currency_symbol} transfers of blocks {start} to \
+         {latest}..."
+    ))?;
+    let progress = ProgressBar::new((start..=latest).count() as u64);
+    for block in start..=latest {
+        let entries = crate::logscan::scan_native(
+            client,
+            history.account,
+            block,
+            currency_symbol,
+        )
+        .await?;
+        history.merge(entries);
+        history.native_checkpoint = Some(block);
+        history.native_start.get_or_insert(block);
+        progress.inc(1);
+    }
+    progress.finish_and_clear();
+    if let (Some(from), Some(start)) = (from_block, history.native_start) {
+        if from < start {
+            term.write_line(&format!(
+                "Scanning the {currency_symbol} transfers of blocks {from} to \
+                 {}...",
+                start - 1
+            ))?;
+            let progress = ProgressBar::new(start - from);
+            for block in (from..start).rev() {
+                let entries = crate::logscan::scan_native(
+                    client,
+                    history.account,
+                    block,
+                    currency_symbol,
+                )
+                .await?;
+                history.merge(entries);
+                history.native_start = Some(block);
+                progress.inc(1);
+            }
+            progress.finish_and_clear();
+        }
+    }
+    Ok(())
+}
+
+/// The number of the block, the tagged blocks (e.g. `latest`) are fetched.
+async fn block_number(
+    client: &EthersClient,
+    block: types::BlockNumber,
+) -> Result<u64> {
+    match block {
+        types::BlockNumber::Number(number) => Ok(number.as_u64()),
+        types::BlockNumber::Earliest => Ok(0),
+        tag => client
+            .get_block(tag)
+            .await
+            .map_err(|e| eyre::eyre!("Failed to fetch block {:?}: {}", tag, e))?
+            .and_then(|block| block.number)
+            .map(|number| number.as_u64())
+            .ok_or_else(|| eyre::eyre!("Block {:?} not found", tag)),
+    }
+}
+
+/// Asks for the passphrase of an encrypted file, confirmed only when the
+/// file is about to be created.
+fn prompt_passphrase(name: &str, exists: bool) -> Result<String> {
+    let message = format!("{name} passphrase");
+    let help_message = format!(
+        "A new {} will be created, encrypted with this passphrase",
+        name.to_lowercase()
+    );
+    let mut prompt = inquire::Password::new(&message)
+        .with_display_mode(inquire::PasswordDisplayMode::Masked);
+    if exists {
+        prompt = prompt.without_confirmation();
+    } else {
+        prompt = prompt.with_help_message(&help_message);
+    }
+    Ok(prompt.prompt()?)
+}
+
+/// The amounts sent by the transaction, the tokens without decimals are
+/// journaled with their raw amount.
+async fn journal_transfers(
+    client: &EthersClient,
+    spending: &crate::policy::Spending,
+) -> Vec<crate::journal::Transfer> {
+    let symbol = |token: types::Address| async move {
+        crate::erc721::Erc721::new(token, Arc::new(client.clone()))
+            .symbol()
+            .call()
+            .await
+            .unwrap_or_else(|_| ethers::utils::to_checksum(&token, None))
+    };
+    let mut transfers = spending
+        .spends
+        .iter()
+        .map(|spend| crate::journal::Transfer {
+            token: spend.token,
+            symbol: spend.symbol.clone(),
+            amount: spend.amount,
+            decimals: spend.decimals,
+            token_id: None,
+        })
+        .collect::<Vec<_>>();
+    if let Some((token, amount)) = spending.unknown_token {
+        transfers.push(crate::journal::Transfer {
+            token: Some(token),
+            symbol: symbol(token).await,
+            amount,
+            decimals: 0,
+            token_id: None,
+        });
+    }
+    for nft in &spending.nfts {
+        transfers.push(crate::journal::Transfer {
+            token: Some(nft.contract),
+            symbol: symbol(nft.contract).await,
+            amount: nft.amount,
+            decimals: 0,
+            token_id: Some(nft.token_id),
+        });
+    }
+    transfers
+}
+
+/// Asks for the journal passphrase and opens the journal, a new one is
+/// created if it does not exist yet.
+fn open_journal() -> Result<(String, crate::journal::Journal)> {
+    let passphrase =
+        prompt_passphrase("Journal", crate::journal::Journal::exists())?;
+    let journal = crate::journal::Journal::load(&passphrase)?;
+    Ok((passphrase, journal))
+}
+
+/// Runs the future to completion from the synchronous prompts, which are
+/// called within the async runtime.
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::task::block_in_place(|| {
+        tokio::runtime::Handle::current().block_on(future)
+    })
+}
+
+/// Asks the user for a JSON payload, either loaded from a file or written
+/// in the editor.
+fn prompt_json(message: &str) -> Result<String> {
+    let from_file = "Load from a file";
+    let from_editor = "Paste in the editor";
+    let source =
+        inquire::Select::new(message, vec![from_file, from_editor]).prompt()?;
+    let json = if source == from_file {
+        let path = inquire::Text::new("JSON file path").prompt()?;
+        std::fs::read_to_string(path.trim())?
+    } else {
+        inquire::Editor::new(message)
+            .with_file_extension(".json")
+            .prompt()?
+    };
+    Ok(json)
+}
+
 fn try_parse_address(
     s: String,
 ) -> Result<types::Address, inquire::InquireError> {
@@ -846,11 +3441,69 @@ fn try_parse_address(
     .map_err(|e| inquire::InquireError::Custom(e.into()))
 }
 
+fn try_parse_u256(s: String) -> Result<types::U256, inquire::InquireError> {
+    types::U256::from_dec_str(s.trim())
+        .map_err(|e| inquire::InquireError::Custom(e.into()))
+}
+
+fn try_parse_block_number(
+    s: String,
+) -> Result<types::BlockNumber, inquire::InquireError> {
+    // block tags are handled by ethers, but it parses numbers as hex
+    // so we handle the decimal numbers ourselves.
+    match s.trim().parse::<u64>() {
+        Ok(n) => Ok(types::BlockNumber::Number(n.into())),
+        Err(_) => s.trim().parse::<types::BlockNumber>(),
+    }
+    .map_err(|e| inquire::InquireError::Custom(e.into()))
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
 
+    #[test]
+    fn address_checksum() {
+        let checksummed = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+        let address = checksummed.parse().unwrap();
+        assert!(check_address_checksum(checksummed, address, true).is_ok());
+        let lowercase = checksummed.to_lowercase();
+        assert!(check_address_checksum(&lowercase, address, false).is_ok());
+        assert!(check_address_checksum(&lowercase, address, true).is_err());
+        let typo = "0x5aaeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+        let err = check_address_checksum(typo, address, false).unwrap_err();
+        assert!(err.ends_with("0x5a[a]eb6053F3E94C9b9A09f33669435E7Ef1BeAed"));
+        let contact = "Friend | 0x5aaeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+        let validator = AddressValidator {
+            require_checksum: false,
+        };
+        assert!(matches!(
+            validator::StringValidator::validate(&validator, contact),
+            Ok(validator::Validation::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn checksummed_json() {
+        let address: types::Address =
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+                .parse()
+                .unwrap();
+        let tx = TypedTransaction::Eip1559(
+            ethers::types::Eip1559TransactionRequest::new()
+                .to(address)
+                .data(address.as_bytes().to_vec()),
+        );
+        let json = to_checksummed_json(&tx).unwrap();
+        assert!(json.contains(
+            "\"to\": \"0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed\""
+        ));
+        assert!(json.contains(
+            "\"data\": \"0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed\""
+        ));
+    }
+
     #[test]
     fn eth_sign_request() {
         let config = crate::config::Config {
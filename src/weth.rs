@@ -0,0 +1,16 @@
+use ethers::contract::abigen;
+
+abigen!(
+    Weth,
+    r#"[
+        function name() public view returns (string memory)
+        function symbol() public view returns (string memory)
+        function decimals() public view returns (uint8)
+        function balanceOf(address account) public view returns (uint256)
+        function deposit() public payable
+        function withdraw(uint256 wad) public
+        event Deposit(address indexed dst, uint256 wad)
+        event Withdrawal(address indexed src, uint256 wad)
+    ]"#,
+    event_derives(serde::Deserialize, serde::Serialize),
+);